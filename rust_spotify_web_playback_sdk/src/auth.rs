@@ -0,0 +1,452 @@
+//! The OAuth Authorization Code with PKCE flow for browser apps, enabled with the `auth` feature.
+//!
+//! [`PkceAuth::login`] redirects to the Spotify login page, [`PkceAuth::handle_callback`] exchanges the code when the user
+//! comes back, and [`PkceAuth::token_provider`] is the closure to pass to [`init`](crate::init) as `oauth`.
+//! The token is kept in sessionStorage or localStorage and refreshed when it expires.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{auth::PkceAuth, prelude as sp};
+//!
+//! let auth = PkceAuth::new("your client id", "http://localhost:8080/callback");
+//! wasm_bindgen_futures::spawn_local(async move {
+//!     if auth.handle_callback().await.unwrap().is_none() && auth.token().is_none() {
+//!         auth.login().unwrap();
+//!         return;
+//!     }
+//!     sp::init(auth.token_provider(), || {}, "example player", 1.0, false);
+//! });
+//! ```
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::SdkError,
+    token::{AccessToken, TokenCache},
+};
+
+pub use crate::SCOPES;
+
+/// The Spotify authorize page
+pub const AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+/// The Spotify token endpoint
+pub const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+
+const TOKEN_KEY: &str = "rust_spotify_web_playback_sdk.token";
+const VERIFIER_KEY: &str = "rust_spotify_web_playback_sdk.verifier";
+const STATE_KEY: &str = "rust_spotify_web_playback_sdk.state";
+/// Refresh the token when it expires in less than this
+const EXPIRY_MARGIN_MS: f64 = 60_000.0;
+
+/// Where the token is kept between page loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStorage {
+    /// `window.sessionStorage`, the token is gone when the tab is closed
+    Session,
+    /// `window.localStorage`, the token survives closing the browser
+    Local,
+    /// Only in memory, the user has to log in again on every page load. Works outside of the browser.
+    Memory,
+}
+
+/// An access token and what is needed to refresh it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// The space separated scopes granted by the user
+    pub scope: String,
+    /// When the token expires, in milliseconds since the unix epoch
+    pub expires_at: u64,
+}
+
+impl Token {
+    /// True if the token expires in less than a minute
+    pub fn expires_soon(&self) -> bool {
+        self.expires_at as f64 - crate::now_ms() < EXPIRY_MARGIN_MS
+    }
+}
+
+/// The PKCE flow for one Spotify app, cloning it is cheap and the clones share the token
+#[derive(Clone)]
+pub struct PkceAuth {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    config: Config,
+    memory: RefCell<HashMap<&'static str, String>>,
+    http: reqwest::Client,
+}
+
+#[derive(Clone)]
+struct Config {
+    client_id: String,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    authorize_url: String,
+    token_url: String,
+    storage: TokenStorage,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    #[serde(default)]
+    scope: String,
+    expires_in: u32,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl PkceAuth {
+    /// Creates the flow for an app, with the scopes in [`SCOPES`] and the token in sessionStorage
+    ///
+    /// # Arguments
+    /// * `client_id` - The client id of the app from the Spotify developer dashboard.
+    /// * `redirect_uri` - Where Spotify sends the user back, it has to be in the app's allowed redirect URIs.
+    pub fn new(client_id: &str, redirect_uri: &str) -> Self {
+        Self::from_config(Config {
+            client_id: client_id.into(),
+            redirect_uri: redirect_uri.into(),
+            scopes: SCOPES.iter().map(|scope| scope.to_string()).collect(),
+            authorize_url: AUTHORIZE_URL.into(),
+            token_url: TOKEN_URL.into(),
+            storage: TokenStorage::Session,
+        })
+    }
+
+    fn from_config(config: Config) -> Self {
+        PkceAuth {
+            inner: Rc::new(Inner {
+                config,
+                memory: RefCell::new(HashMap::new()),
+                http: reqwest::Client::new(),
+            }),
+        }
+    }
+
+    /// Requests `scopes` in addition to the ones in [`SCOPES`]
+    pub fn with_scopes(self, scopes: &[&str]) -> Self {
+        self.map_config(|config| {
+            for scope in scopes {
+                if !config.scopes.iter().any(|s| s == scope) {
+                    config.scopes.push(scope.to_string());
+                }
+            }
+        })
+    }
+
+    /// Keeps the token in `storage` instead of sessionStorage
+    pub fn with_storage(self, storage: TokenStorage) -> Self {
+        self.map_config(|config| config.storage = storage)
+    }
+
+    /// Uses another authorize page and token endpoint, useful for proxies and mock servers
+    pub fn with_urls(self, authorize_url: &str, token_url: &str) -> Self {
+        self.map_config(|config| {
+            config.authorize_url = authorize_url.into();
+            config.token_url = token_url.into();
+        })
+    }
+
+    fn map_config(self, f: impl FnOnce(&mut Config)) -> Self {
+        let mut config = self.inner.config.clone();
+        f(&mut config);
+        Self::from_config(config)
+    }
+
+    /// The url of the Spotify login page for the given PKCE challenge and state
+    pub fn authorize_url(&self, code_challenge: &str, state: &str) -> String {
+        let config = &self.inner.config;
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("client_id", &config.client_id)
+            .append_pair("response_type", "code")
+            .append_pair("redirect_uri", &config.redirect_uri)
+            .append_pair("scope", &config.scopes.join(" "))
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("state", state)
+            .finish();
+        format!("{}?{}", config.authorize_url, query)
+    }
+
+    /// Redirects the browser to the Spotify login page, the verifier is kept in sessionStorage until the user comes back
+    pub fn login(&self) -> Result<(), SdkError> {
+        let verifier = code_verifier()?;
+        let state = random_string(16)?;
+        self.set_item(TokenStorage::Session, VERIFIER_KEY, &verifier)?;
+        self.set_item(TokenStorage::Session, STATE_KEY, &state)?;
+        let url = self.authorize_url(&code_challenge(&verifier), &state);
+        window()?
+            .location()
+            .set_href(&url)
+            .map_err(|e| SdkError::Auth(format!("{:#?}", e)))
+    }
+
+    /// Handles the redirect back from the Spotify login page.
+    ///
+    /// # Response
+    /// `None` if the current url is not a login callback, the new token otherwise.
+    /// The `code` and `state` parameters are removed from the url.
+    pub async fn handle_callback(&self) -> Result<Option<Token>, SdkError> {
+        let window = window()?;
+        let search = window
+            .location()
+            .search()
+            .map_err(|e| SdkError::Auth(format!("{:#?}", e)))?;
+        let Some(params) = parse_callback(&search)? else {
+            return Ok(None);
+        };
+        if let Ok(history) = window.history() {
+            if let Ok(path) = window.location().pathname() {
+                let _ =
+                    history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&path));
+            }
+        }
+        let expected_state = self.get_item(TokenStorage::Session, STATE_KEY);
+        let verifier = self.get_item(TokenStorage::Session, VERIFIER_KEY);
+        self.remove_item(TokenStorage::Session, STATE_KEY);
+        self.remove_item(TokenStorage::Session, VERIFIER_KEY);
+        if expected_state.as_deref() != Some(params.state.as_str()) {
+            return Err(SdkError::Auth(
+                "the state of the callback doesn't match".into(),
+            ));
+        }
+        let verifier =
+            verifier.ok_or_else(|| SdkError::Auth("the code verifier is missing".into()))?;
+        self.exchange_code(&params.code, &verifier).await.map(Some)
+    }
+
+    /// Exchanges an authorization code for a token, and stores it
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> Result<Token, SdkError> {
+        let config = &self.inner.config;
+        let token = self
+            .request_token(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &config.redirect_uri),
+                ("client_id", &config.client_id),
+                ("code_verifier", code_verifier),
+            ])
+            .await?;
+        self.store_token(&token)?;
+        Ok(token)
+    }
+
+    /// Gets a new access token with the stored refresh token, and stores it
+    pub async fn refresh(&self) -> Result<Token, SdkError> {
+        let refresh_token = self
+            .token()
+            .and_then(|token| token.refresh_token)
+            .ok_or_else(|| SdkError::Auth("there is no refresh token".into()))?;
+        let mut token = self
+            .request_token(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+                ("client_id", &self.inner.config.client_id),
+            ])
+            .await?;
+        // spotify doesn't always rotate the refresh token
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token);
+        }
+        self.store_token(&token)?;
+        Ok(token)
+    }
+
+    /// The stored token, it can be expired
+    pub fn token(&self) -> Option<Token> {
+        self.get_item(self.inner.config.storage, TOKEN_KEY)
+            .and_then(|token| serde_json::from_str(&token).ok())
+    }
+
+    /// Stores a token obtained some other way
+    pub fn store_token(&self, token: &Token) -> Result<(), SdkError> {
+        let token = serde_json::to_string(token).map_err(|e| SdkError::Auth(e.to_string()))?;
+        self.set_item(self.inner.config.storage, TOKEN_KEY, &token)
+    }
+
+    /// Removes the stored token
+    pub fn logout(&self) {
+        self.remove_item(self.inner.config.storage, TOKEN_KEY);
+    }
+
+    /// A [`TokenCache`] that starts with the stored token, and refreshes it with the refresh token before it expires
+    pub fn token_cache(&self) -> TokenCache {
+        let auth = self.clone();
+        let cache = TokenCache::new(move || {
+            let auth = auth.clone();
+            async move {
+                let token = auth.refresh().await?;
+                Ok(AccessToken {
+                    expires_in: (token.expires_at.saturating_sub(crate::now_ms() as u64) / 1000)
+                        as u32,
+                    access_token: token.access_token,
+                })
+            }
+        });
+        if let Some(token) = self.token() {
+            cache.set_token(&token.access_token, token.expires_at);
+        }
+        cache
+    }
+
+    /// The closure to pass to [`init`](crate::init) as `oauth`.
+    /// It returns the stored access token, and refreshes it before it expires, see [`token_cache`](PkceAuth::token_cache).
+    /// Without a stored token it returns an empty string, log in or await [`refresh`](PkceAuth::refresh) first.
+    pub fn token_provider(&self) -> impl FnMut() -> String + 'static {
+        self.token_cache().provider()
+    }
+
+    async fn request_token(&self, form: &[(&str, &str)]) -> Result<Token, SdkError> {
+        let response = self
+            .inner
+            .http
+            .post(&self.inner.config.token_url)
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| SdkError::Request(e.to_string()))?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| SdkError::Request(e.to_string()))?;
+        if !(200..300).contains(&status) {
+            return Err(match serde_json::from_str::<TokenError>(&body) {
+                Ok(error) => SdkError::Api {
+                    status,
+                    message: error
+                        .error_description
+                        .unwrap_or_else(|| error.error.clone()),
+                    reason: Some(error.error),
+                },
+                Err(_) => SdkError::Api {
+                    status,
+                    message: body,
+                    reason: None,
+                },
+            });
+        }
+        let response: TokenResponse =
+            serde_json::from_str(&body).map_err(|e| SdkError::Deserialize(e.to_string()))?;
+        Ok(Token {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            scope: response.scope,
+            expires_at: crate::now_ms() as u64 + u64::from(response.expires_in) * 1000,
+        })
+    }
+
+    fn get_item(&self, storage: TokenStorage, key: &'static str) -> Option<String> {
+        match self.storage(storage) {
+            Some(storage) => storage.get_item(key).ok().flatten(),
+            None => self.inner.memory.borrow().get(key).cloned(),
+        }
+    }
+
+    fn set_item(
+        &self,
+        storage: TokenStorage,
+        key: &'static str,
+        value: &str,
+    ) -> Result<(), SdkError> {
+        match self.storage(storage) {
+            Some(storage) => storage
+                .set_item(key, value)
+                .map_err(|e| SdkError::Auth(format!("{:#?}", e))),
+            None => {
+                self.inner.memory.borrow_mut().insert(key, value.into());
+                Ok(())
+            }
+        }
+    }
+
+    fn remove_item(&self, storage: TokenStorage, key: &'static str) {
+        match self.storage(storage) {
+            Some(storage) => {
+                let _ = storage.remove_item(key);
+            }
+            None => {
+                self.inner.memory.borrow_mut().remove(key);
+            }
+        }
+    }
+
+    /// The browser storage for `storage`, `None` for memory or outside of the browser
+    fn storage(&self, storage: TokenStorage) -> Option<web_sys::Storage> {
+        // the verifier follows the token into memory, so the flow works without a browser
+        if self.inner.config.storage == TokenStorage::Memory || !cfg!(target_arch = "wasm32") {
+            return None;
+        }
+        let window = web_sys::window()?;
+        match storage {
+            TokenStorage::Session => window.session_storage().ok().flatten(),
+            TokenStorage::Local => window.local_storage().ok().flatten(),
+            TokenStorage::Memory => None,
+        }
+    }
+}
+
+/// The `code` and `state` of a login callback
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallbackParams {
+    pub code: String,
+    pub state: String,
+}
+
+/// Parses the query string of the redirect uri.
+///
+/// # Response
+/// `None` if there is no `code` parameter, [`SdkError::Auth`] if Spotify sent an `error` parameter.
+pub fn parse_callback(query: &str) -> Result<Option<CallbackParams>, SdkError> {
+    let mut code = None;
+    let mut state = None;
+    for (key, value) in url::form_urlencoded::parse(query.trim_start_matches('?').as_bytes()) {
+        match key.as_ref() {
+            "code" => code = Some(value.into_owned()),
+            "state" => state = Some(value.into_owned()),
+            "error" => return Err(SdkError::Auth(value.into_owned())),
+            _ => {}
+        }
+    }
+    Ok(code.map(|code| CallbackParams {
+        code,
+        state: state.unwrap_or_default(),
+    }))
+}
+
+/// A random PKCE code verifier, 64 characters long
+pub fn code_verifier() -> Result<String, SdkError> {
+    random_string(64)
+}
+
+/// The S256 PKCE code challenge of `verifier`
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn random_string(len: usize) -> Result<String, SdkError> {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| SdkError::Auth(e.to_string()))?;
+    Ok(bytes
+        .into_iter()
+        .map(|byte| CHARS[byte as usize % CHARS.len()] as char)
+        .collect())
+}
+
+/// The window of the page, [`SdkError::NotInBrowser`] on native targets, with the [`SimulatedPlayer`](crate::simulated::SimulatedPlayer) too
+fn window() -> Result<web_sys::Window, SdkError> {
+    crate::ensure_event_loop()?;
+    web_sys::window().ok_or(SdkError::NotInBrowser)
+}
@@ -0,0 +1,377 @@
+//! The player the crate talks to. By default it's the Web Playback SDK through `wrapper.js`,
+//! [`set_backend`] swaps it for another one, like the [`SimulatedPlayer`](crate::simulated::SimulatedPlayer),
+//! so apps can be developed and tested without a Premium account, a browser or the network.
+//!
+//! Every function of the crate (the commands, the store, the confirmations, the supervisors) goes through the backend,
+//! except `add_listener!`, which always talks to the Web Playback SDK. Use [`add_listener`] to listen to any backend.
+//! The supervisors need the browser's event loop, on native targets they fail with [`SdkError::NotInBrowser`](crate::error::SdkError::NotInBrowser) with any backend.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{backend, prelude as sp, simulated::{self, SimulatedPlayer}};
+//!
+//! // install it before init
+//! let player = SimulatedPlayer::new(vec![simulated::track("1", "First song", 180_000)]);
+//! backend::set_backend(player.clone());
+//! sp::init(|| "token".into(), || {}, "test player", 1.0, false);
+//! sp::connect().await?;
+//! sp::resume().await?;
+//! player.advance(10_000);
+//! ```
+use std::{cell::RefCell, rc::Rc};
+
+use futures::future::{FutureExt, LocalBoxFuture};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    js_wrapper,
+    structs::{
+        state_change::StateChange,
+        web_playback::{Error, Player, State},
+    },
+    InitOptions,
+};
+
+/// What the async methods of [`PlayerBackend`] return, the error is the message of the rejected promise
+pub type BackendFuture<T> = LocalBoxFuture<'static, Result<T, String>>;
+
+/// A listener added with [`PlayerBackend::add_listener`]
+pub type Listener = Box<dyn FnMut(SdkEvent)>;
+
+/// An event of the player, with the payload the SDK passes to its listeners
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdkEvent {
+    Ready(Player),
+    NotReady(Player),
+    /// `None` when playback moved to another device
+    PlayerStateChanged(Option<Box<StateChange>>),
+    AutoplayFailed,
+    InitializationError(Error),
+    AuthenticationError(Error),
+    AccountError(Error),
+    PlaybackError(Error),
+}
+
+impl SdkEvent {
+    /// The name of the event, like `"player_state_changed"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            SdkEvent::Ready(_) => "ready",
+            SdkEvent::NotReady(_) => "not_ready",
+            SdkEvent::PlayerStateChanged(_) => "player_state_changed",
+            SdkEvent::AutoplayFailed => "autoplay_failed",
+            SdkEvent::InitializationError(_) => "initialization_error",
+            SdkEvent::AuthenticationError(_) => "authentication_error",
+            SdkEvent::AccountError(_) => "account_error",
+            SdkEvent::PlaybackError(_) => "playback_error",
+        }
+    }
+
+    /// The error event called `event`, `None` if it isn't one
+    pub fn error(event: &str, error: Error) -> Option<Self> {
+        Some(match event {
+            "initialization_error" => SdkEvent::InitializationError(error),
+            "authentication_error" => SdkEvent::AuthenticationError(error),
+            "account_error" => SdkEvent::AccountError(error),
+            "playback_error" => SdkEvent::PlaybackError(error),
+            _ => return None,
+        })
+    }
+
+    /// Parses the payload the SDK passed to the listener of `event`.
+    /// A payload that doesn't have the expected shape is logged and dropped, a listener can't return the error.
+    fn from_js(event: &str, payload: JsValue) -> Option<Self> {
+        let value = payload.clone();
+        let parsed = match event {
+            "ready" => {
+                serde_wasm_bindgen::from_value(value).map(|player| Some(SdkEvent::Ready(player)))
+            }
+            "not_ready" => {
+                serde_wasm_bindgen::from_value(value).map(|player| Some(SdkEvent::NotReady(player)))
+            }
+            "player_state_changed" => serde_wasm_bindgen::from_value(value)
+                .map(|state| Some(SdkEvent::PlayerStateChanged(state))),
+            "autoplay_failed" => Ok(Some(SdkEvent::AutoplayFailed)),
+            _ => serde_wasm_bindgen::from_value(value).map(|error| SdkEvent::error(event, error)),
+        };
+        match parsed {
+            Ok(event) => event,
+            Err(e) => {
+                web_sys::console::warn_2(
+                    &format!(
+                        "rust_spotify_web_playback_sdk: dropped a malformed {event} event: {e}"
+                    )
+                    .into(),
+                    &payload,
+                );
+                None
+            }
+        }
+    }
+}
+
+/// The payloads [`crate::listen`] can pass to its listeners
+pub(crate) trait Payload: Sized {
+    fn from_event(event: SdkEvent) -> Option<Self>;
+}
+
+impl Payload for SdkEvent {
+    fn from_event(event: SdkEvent) -> Option<Self> {
+        Some(event)
+    }
+}
+
+impl Payload for Player {
+    fn from_event(event: SdkEvent) -> Option<Self> {
+        match event {
+            SdkEvent::Ready(player) | SdkEvent::NotReady(player) => Some(player),
+            _ => None,
+        }
+    }
+}
+
+impl Payload for Error {
+    fn from_event(event: SdkEvent) -> Option<Self> {
+        match event {
+            SdkEvent::InitializationError(error)
+            | SdkEvent::AuthenticationError(error)
+            | SdkEvent::AccountError(error)
+            | SdkEvent::PlaybackError(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Payload for Option<StateChange> {
+    fn from_event(event: SdkEvent) -> Option<Self> {
+        match event {
+            SdkEvent::PlayerStateChanged(state) => Some(state.map(|state| *state)),
+            _ => None,
+        }
+    }
+}
+
+/// The methods of the `Spotify.Player` class, see the functions of the crate for what each one does
+pub trait PlayerBackend {
+    /// False where the backend can't run, then the commands fail with [`SdkError::NotInBrowser`](crate::error::SdkError::NotInBrowser)
+    fn available(&self) -> bool {
+        true
+    }
+
+    /// Creates the player, `on_ready` is called once it exists
+    fn init(
+        &self,
+        oauth: Box<dyn FnMut() -> String>,
+        on_ready: Box<dyn FnMut()>,
+        options: &InitOptions,
+    );
+
+    /// True once the player exists
+    fn player_ready(&self) -> bool;
+
+    /// Resolves with the success of the connection
+    fn connect(&self) -> BackendFuture<bool>;
+
+    fn disconnect(&self);
+
+    /// Returns true if the event name is valid
+    fn add_listener(&self, event: &str, callback: Listener) -> bool;
+
+    /// Removes every listener of `event`, returns true if the event name is valid.
+    /// The crate adds its own listener back afterwards.
+    fn remove_listener(&self, event: &str) -> bool;
+
+    fn get_current_state(&self) -> BackendFuture<Option<State>>;
+
+    fn set_name(&self, name: String) -> BackendFuture<()>;
+
+    fn get_volume(&self) -> BackendFuture<f32>;
+
+    fn set_volume(&self, volume: f32) -> BackendFuture<()>;
+
+    fn pause(&self) -> BackendFuture<()>;
+
+    fn resume(&self) -> BackendFuture<()>;
+
+    fn toggle_play(&self) -> BackendFuture<()>;
+
+    fn seek(&self, position_ms: u32) -> BackendFuture<()>;
+
+    fn previous_track(&self) -> BackendFuture<()>;
+
+    fn next_track(&self) -> BackendFuture<()>;
+
+    fn activate_element(&self) -> BackendFuture<()>;
+}
+
+thread_local! {
+    static BACKEND: RefCell<Rc<dyn PlayerBackend>> = RefCell::new(Rc::new(WebPlaybackSdk));
+}
+
+/// Replaces the backend, call it before `init`. The listeners added to the old backend stay there.
+pub fn set_backend<B>(backend: B)
+where
+    B: PlayerBackend + 'static,
+{
+    BACKEND.with(|current| *current.borrow_mut() = Rc::new(backend));
+    crate::forget_own_listeners();
+}
+
+/// The backend the crate talks to
+pub(crate) fn current() -> Rc<dyn PlayerBackend> {
+    BACKEND.with(|current| current.borrow().clone())
+}
+
+/// Like `add_listener!`, but through the installed backend, so it works with the [`SimulatedPlayer`](crate::simulated::SimulatedPlayer) too.
+///
+/// # Response
+/// Returns true if the event name is valid.
+///
+/// # Errors
+/// [`SdkError::PlayerNotReady`](crate::error::SdkError::PlayerNotReady) if the player doesn't exist yet,
+/// [`SdkError::NotInBrowser`](crate::error::SdkError::NotInBrowser) if the backend can't run here.
+///
+/// # Arguments
+/// * `event` - A valid event name. See Web Playback SDK Events.
+/// * `callback` - Called with every event called `event`.
+pub fn add_listener<F>(event: &str, callback: F) -> Result<bool, crate::error::SdkError>
+where
+    F: FnMut(SdkEvent) + 'static,
+{
+    crate::ensure_ready()?;
+    Ok(current().add_listener(event, Box::new(callback)))
+}
+
+/// The Web Playback SDK, through `wrapper.js`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebPlaybackSdk;
+
+/// Awaits a promise of the SDK, a rejection is formatted like the crate always did
+async fn settle(promise: js_sys::Promise) -> Result<JsValue, String> {
+    JsFuture::from(promise)
+        .await
+        .map_err(|e| format!("{:#?}", e))
+}
+
+fn empty(promise: js_sys::Promise) -> BackendFuture<()> {
+    settle(promise)
+        .map(|result| result.map(|_| ()))
+        .boxed_local()
+}
+
+impl PlayerBackend for WebPlaybackSdk {
+    fn available(&self) -> bool {
+        cfg!(target_arch = "wasm32")
+    }
+
+    fn init(
+        &self,
+        oauth: Box<dyn FnMut() -> String>,
+        on_ready: Box<dyn FnMut()>,
+        options: &InitOptions,
+    ) {
+        //leak these closures so they don't get cleaned up
+        let oauth = Box::leak(Box::new(Closure::wrap(oauth)));
+        let on_ready = Box::leak(Box::new(Closure::wrap(on_ready)));
+        js_wrapper::init(
+            oauth,
+            on_ready,
+            options.name.clone(),
+            options.volume,
+            options.enable_media_session,
+        );
+    }
+
+    fn player_ready(&self) -> bool {
+        js_wrapper::player_ready()
+    }
+
+    fn connect(&self) -> BackendFuture<bool> {
+        let promise = js_wrapper::connect();
+        async move {
+            let result = settle(promise).await?;
+            result
+                .as_bool()
+                .ok_or_else(|| format!("not bool, error: {:#?}", result))
+        }
+        .boxed_local()
+    }
+
+    fn disconnect(&self) {
+        js_wrapper::disconnect();
+    }
+
+    fn add_listener(&self, event: &str, mut callback: Listener) -> bool {
+        let name = event.to_string();
+        let closure = Closure::new(move |payload: JsValue| {
+            if let Some(event) = SdkEvent::from_js(&name, payload) {
+                callback(event)
+            }
+        });
+        let closure = Box::leak(Box::new(closure)) as &'static Closure<dyn FnMut(JsValue)>;
+        js_wrapper::addListener(event.into(), closure)
+    }
+
+    fn remove_listener(&self, event: &str) -> bool {
+        js_wrapper::removeListener(event.into())
+    }
+
+    fn get_current_state(&self) -> BackendFuture<Option<State>> {
+        let promise = js_wrapper::getCurrentState();
+        async move {
+            let result = settle(promise).await?;
+            if result.is_null() {
+                return Ok(None);
+            }
+            serde_wasm_bindgen::from_value(result)
+                .map(Some)
+                .map_err(|e| e.to_string())
+        }
+        .boxed_local()
+    }
+
+    fn set_name(&self, name: String) -> BackendFuture<()> {
+        empty(js_wrapper::setName(name))
+    }
+
+    fn get_volume(&self) -> BackendFuture<f32> {
+        let promise = js_wrapper::getVolume();
+        async move {
+            serde_wasm_bindgen::from_value(settle(promise).await?).map_err(|e| format!("{:#?}", e))
+        }
+        .boxed_local()
+    }
+
+    fn set_volume(&self, volume: f32) -> BackendFuture<()> {
+        empty(js_wrapper::setVolume(volume))
+    }
+
+    fn pause(&self) -> BackendFuture<()> {
+        empty(js_wrapper::pause())
+    }
+
+    fn resume(&self) -> BackendFuture<()> {
+        empty(js_wrapper::resume())
+    }
+
+    fn toggle_play(&self) -> BackendFuture<()> {
+        empty(js_wrapper::togglePlay())
+    }
+
+    fn seek(&self, position_ms: u32) -> BackendFuture<()> {
+        empty(js_wrapper::seek(position_ms))
+    }
+
+    fn previous_track(&self) -> BackendFuture<()> {
+        empty(js_wrapper::previousTrack())
+    }
+
+    fn next_track(&self) -> BackendFuture<()> {
+        empty(js_wrapper::nextTrack())
+    }
+
+    fn activate_element(&self) -> BackendFuture<()> {
+        empty(js_wrapper::activateElement())
+    }
+}
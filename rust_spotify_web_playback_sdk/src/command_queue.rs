@@ -0,0 +1,237 @@
+//! A queue that sends the player commands one at a time, so double clicking "next" or dragging a seek bar
+//! doesn't send overlapping promises to the SDK.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::command_queue::{Command, CommandQueue};
+//!
+//! let queue = CommandQueue::new();
+//! // only the last seek that is still waiting in the queue is sent, the others fail with SdkError::Cancelled
+//! wasm_bindgen_futures::spawn_local(async move {
+//!     let _ = queue.send(Command::Seek(30_000)).await;
+//! });
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    mem::discriminant,
+    rc::Rc,
+};
+
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+
+use crate::error::SdkError;
+
+/// How long a command can run before it fails with [`SdkError::Timeout`], by default
+pub const DEFAULT_COMMAND_TIMEOUT_MS: u32 = 5000;
+
+/// A player command that can go through the [`CommandQueue`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    TogglePlay,
+    /// Seek to the position in milliseconds, supersedes the seeks still waiting in the queue
+    Seek(u32),
+    /// Set the volume between 0 and 1, supersedes the volume changes still waiting in the queue
+    SetVolume(f32),
+    NextTrack,
+    PreviousTrack,
+}
+
+impl Command {
+    /// True if only the last command of this kind in the queue matters
+    fn coalesces(&self) -> bool {
+        matches!(self, Command::Seek(_) | Command::SetVolume(_))
+    }
+
+    pub(crate) async fn run(self) -> Result<(), SdkError> {
+        match self {
+            Command::Pause => crate::pause().await,
+            Command::Resume => crate::resume().await,
+            Command::TogglePlay => crate::toggle_play().await,
+            Command::Seek(position_ms) => crate::seek(position_ms).await,
+            Command::SetVolume(volume) => crate::set_volume(volume).await,
+            Command::NextTrack => crate::next_track().await,
+            Command::PreviousTrack => crate::previous_track().await,
+        }
+    }
+}
+
+struct Pending {
+    id: u64,
+    command: Command,
+    /// Gets `None` when it's the command's turn to run, or the error it fails with without running
+    turn: oneshot::Sender<Option<SdkError>>,
+}
+
+/// Runs the commands sent to it one at a time, cloning it is cheap and the clones share the queue.
+/// There is no task running the queue, every command runs in the future that sent it once it's its turn,
+/// so the queue works wherever the futures are awaited, without a browser.
+#[derive(Clone)]
+pub struct CommandQueue {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    pending: RefCell<VecDeque<Pending>>,
+    /// The id of the command that is running
+    running: Cell<Option<u64>>,
+    next_id: Cell<u64>,
+    timeout_ms: Cell<u32>,
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandQueue {
+    /// Creates an empty queue with the [`DEFAULT_COMMAND_TIMEOUT_MS`] timeout
+    pub fn new() -> Self {
+        CommandQueue {
+            inner: Rc::new(Inner {
+                pending: RefCell::new(VecDeque::new()),
+                running: Cell::new(None),
+                next_id: Cell::new(0),
+                timeout_ms: Cell::new(DEFAULT_COMMAND_TIMEOUT_MS),
+            }),
+        }
+    }
+
+    /// Uses `ms` as the timeout of the commands sent with [`send`](CommandQueue::send)
+    pub fn with_timeout(self, ms: u32) -> Self {
+        self.inner.timeout_ms.set(ms);
+        self
+    }
+
+    /// Queues a command with the queue's timeout, see [`send_with_timeout`](CommandQueue::send_with_timeout)
+    pub async fn send(&self, command: Command) -> Result<(), SdkError> {
+        self.send_with_timeout(command, self.inner.timeout_ms.get())
+            .await
+    }
+
+    /// Queues a command, it runs once the commands before it finished.
+    /// Dropping the returned future before the command runs removes it from the queue.
+    ///
+    /// # Response
+    /// Resolves when the SDK finished the command,
+    /// [`SdkError::Cancelled`] if a newer command superseded it or the queue was cleared,
+    /// [`SdkError::Timeout`] if the SDK didn't finish it in `timeout_ms`,
+    /// [`SdkError::NotInBrowser`] where the backend can't run, like the Web Playback SDK on native targets.
+    ///
+    /// # Arguments
+    /// * `command` - The command to run.
+    /// * `timeout_ms` - How long the command can run.
+    pub async fn send_with_timeout(
+        &self,
+        command: Command,
+        timeout_ms: u32,
+    ) -> Result<(), SdkError> {
+        crate::ensure_browser()?;
+        let id = self.inner.next_id.get();
+        self.inner.next_id.set(id + 1);
+        let (turn, receiver) = oneshot::channel();
+        let superseded = {
+            let mut pending = self.inner.pending.borrow_mut();
+            let superseded = if command.coalesces() {
+                let (superseded, kept): (VecDeque<Pending>, VecDeque<Pending>) =
+                    std::mem::take(&mut *pending)
+                        .into_iter()
+                        .partition(|pending| {
+                            discriminant(&pending.command) == discriminant(&command)
+                        });
+                *pending = kept;
+                superseded
+            } else {
+                VecDeque::new()
+            };
+            pending.push_back(Pending { id, command, turn });
+            superseded
+        };
+        for pending in superseded {
+            let _ = pending.turn.send(Some(SdkError::Cancelled));
+        }
+        // hands the turn to the next command when this one finishes, or when its future is dropped
+        let _turn = Turn {
+            inner: self.inner.clone(),
+            id,
+        };
+        self.inner.advance();
+        if let Some(error) = receiver.await.unwrap_or(Some(SdkError::Cancelled)) {
+            return Err(error);
+        }
+        if !crate::player_ready() {
+            return Err(SdkError::PlayerNotReady);
+        }
+        let command = Box::pin(command.run());
+        let timeout = Box::pin(crate::sleep(timeout_ms));
+        match select(command, timeout).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(SdkError::Timeout),
+        }
+    }
+
+    /// The number of commands waiting in the queue, without the one that is running
+    pub fn len(&self) -> usize {
+        self.inner.pending.borrow().len()
+    }
+
+    /// True if no command is waiting in the queue
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cancels every command waiting in the queue, the one that is running finishes
+    pub fn clear(&self) {
+        let cleared: Vec<Pending> = self.inner.pending.borrow_mut().drain(..).collect();
+        for pending in cleared {
+            let _ = pending.turn.send(Some(SdkError::Cancelled));
+        }
+    }
+}
+
+impl Inner {
+    /// Gives the turn to the first waiting command if none is running,
+    /// skipping the ones whose future was dropped
+    fn advance(&self) {
+        if self.running.get().is_some() {
+            return;
+        }
+        loop {
+            let next = self.pending.borrow_mut().pop_front();
+            let Some(pending) = next else {
+                return;
+            };
+            if pending.turn.send(None).is_ok() {
+                self.running.set(Some(pending.id));
+                return;
+            }
+        }
+    }
+}
+
+/// Held by the future of a command until it finishes or is dropped
+struct Turn {
+    inner: Rc<Inner>,
+    id: u64,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        if self.inner.running.get() == Some(self.id) {
+            self.inner.running.set(None);
+            self.inner.advance();
+        } else {
+            // nobody will wait for this command anymore
+            self.inner
+                .pending
+                .borrow_mut()
+                .retain(|pending| pending.id != self.id);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Unstyled Leptos components for the usual player UI, requires the `components` feature.
+//! They read the signals of [`provide_spotify_player`](crate::leptos::provide_spotify_player), so put them under it.
+//! Every component has a `spotify-*` class on its root element for theming, and an optional `class` prop that is added to it.
+//!
+//! # Example
+//! ```rust,ignore
+//! use leptos::*;
+//! use rust_spotify_web_playback_sdk::{components::*, leptos::*};
+//!
+//! #[component]
+//! fn App() -> impl IntoView {
+//!     provide_spotify_player(SpotifyPlayerOptions::new("example player", || "token".to_string()));
+//!     view! {
+//!         <NowPlaying/>
+//!         <TransportControls/>
+//!         <SeekBar/>
+//!         <VolumeSlider/>
+//!         <QueuePreview/>
+//!     }
+//! }
+//! ```
+use ::leptos::{
+    component, create_memo, create_rw_signal, event_target_value, view, Action, CollectView,
+    IntoView, RwSignal, SignalGet, SignalSet, SignalWith,
+};
+
+use crate::{
+    command_queue::Command,
+    error::SdkError,
+    leptos::{command_action, use_player_state, use_position, use_volume},
+    restrictions::Capabilities,
+    structs::state_change::StateChange,
+};
+
+/// The base class followed by the `class` prop
+fn class_name(base: &str, class: Option<String>) -> String {
+    match class {
+        Some(class) => format!("{base} {class}"),
+        None => base.into(),
+    }
+}
+
+/// Milliseconds as `m:ss`
+fn format_ms(ms: i32) -> String {
+    let seconds = ms.max(0) / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Which controls the current state allows, the components disable the others
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnabledControls {
+    pub previous: bool,
+    /// Resuming when paused, pausing when playing
+    pub play_pause: bool,
+    pub next: bool,
+    pub seek: bool,
+}
+
+impl EnabledControls {
+    /// Everything is disabled without playback
+    pub fn from_state(state: Option<&StateChange>) -> Self {
+        let Some(state) = state else {
+            return Self::default();
+        };
+        let capabilities = Capabilities::from_state(state);
+        Self {
+            previous: capabilities.can_skip_prev(),
+            play_pause: if state.paused {
+                capabilities.can_resume()
+            } else {
+                capabilities.can_pause()
+            },
+            next: capabilities.can_skip_next(),
+            seek: capabilities.can_seek(),
+        }
+    }
+}
+
+/// The value of a range input while the user drags it, the action is only dispatched once it's released.
+/// Keeps the updates of the signal the input shows from moving the thumb under the pointer.
+pub struct RangeDrag<T: 'static> {
+    dragging: RwSignal<Option<i32>>,
+    action: Action<T, Result<(), SdkError>>,
+}
+
+impl<T> Clone for RangeDrag<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RangeDrag<T> {}
+
+impl<T> RangeDrag<T> {
+    pub fn new(action: Action<T, Result<(), SdkError>>) -> Self {
+        Self {
+            dragging: create_rw_signal(None),
+            action,
+        }
+    }
+
+    /// The dragged value, or `current` when the user isn't dragging
+    pub fn value(&self, current: impl FnOnce() -> i32) -> i32 {
+        self.dragging.get().unwrap_or_else(current)
+    }
+
+    /// For the `input` events, the thumb moved
+    pub fn input(&self, value: &str) {
+        self.dragging.set(value.parse().ok());
+    }
+
+    /// For the `change` event, the thumb was released: dispatches the action with `to_input` of the value
+    pub fn change(&self, value: &str, to_input: impl FnOnce(i32) -> T) {
+        if let Ok(value) = value.parse() {
+            self.action.dispatch(to_input(value));
+        }
+        self.dragging.set(None);
+    }
+}
+
+/// The album art, the title and the artists of the current track. Renders nothing without playback.
+#[component]
+pub fn NowPlaying(
+    /// The album art closest to this width in pixels is picked from `Album.images`
+    #[prop(default = 300)]
+    image_size: i32,
+    #[prop(optional, into)] class: Option<String>,
+) -> impl IntoView {
+    let state = use_player_state();
+    let class = class_name("spotify-now-playing", class);
+    move || {
+        state.with(|state| {
+            state.as_ref().map(|state| {
+                let track = &state.track_window.current_track;
+                let image = track.album.image(image_size).map(|image| {
+                    view! {
+                        <img
+                            class="spotify-now-playing-art"
+                            src=image.url.clone()
+                            alt=format!("Album art of {}", track.album.name)
+                        />
+                    }
+                });
+                let artists = track
+                    .artists
+                    .iter()
+                    .map(|artist| artist.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                view! {
+                    <div class=class.clone()>
+                        {image}
+                        <span class="spotify-now-playing-title">{track.name.clone()}</span>
+                        <span class="spotify-now-playing-artists">{artists}</span>
+                    </div>
+                }
+            })
+        })
+    }
+}
+
+/// Previous, play/pause and next buttons, disabled when the current state disallows them
+#[component]
+pub fn TransportControls(#[prop(optional, into)] class: Option<String>) -> impl IntoView {
+    let state = use_player_state();
+    let previous_track = command_action(|_: &()| Command::PreviousTrack);
+    let toggle_play = command_action(|_: &()| Command::TogglePlay);
+    let next_track = command_action(|_: &()| Command::NextTrack);
+    let enabled =
+        create_memo(move |_| state.with(|state| EnabledControls::from_state(state.as_ref())));
+    let paused = move || state.with(|state| state.as_ref().is_none_or(|state| state.paused));
+
+    view! {
+        <div class=class_name("spotify-transport-controls", class) role="group" aria-label="Playback controls">
+            <button
+                class="spotify-previous"
+                aria-label="Previous track"
+                disabled=move || !enabled.get().previous
+                on:click=move |_| previous_track.dispatch(())
+            >
+                "⏮"
+            </button>
+            <button
+                class="spotify-play-pause"
+                aria-label=move || if paused() { "Play" } else { "Pause" }
+                aria-pressed=move || (!paused()).to_string()
+                disabled=move || !enabled.get().play_pause
+                on:click=move |_| toggle_play.dispatch(())
+            >
+                {move || if paused() { "▶" } else { "⏸" }}
+            </button>
+            <button
+                class="spotify-next"
+                aria-label="Next track"
+                disabled=move || !enabled.get().next
+                on:click=move |_| next_track.dispatch(())
+            >
+                "⏭"
+            </button>
+        </div>
+    }
+}
+
+/// A range input with the interpolated position, it seeks when the user releases it
+#[component]
+pub fn SeekBar(#[prop(optional, into)] class: Option<String>) -> impl IntoView {
+    let state = use_player_state();
+    let position = use_position();
+    let drag = RangeDrag::new(command_action(|position_ms: &u32| {
+        Command::Seek(*position_ms)
+    }));
+    let duration = move || state.with(|state| state.as_ref().map_or(0, |state| state.duration));
+    let can_seek = move || state.with(|state| EnabledControls::from_state(state.as_ref()).seek);
+    let value = move || drag.value(|| position.get());
+
+    view! {
+        <div class=class_name("spotify-seek-bar", class)>
+            <span class="spotify-seek-bar-position">{move || format_ms(value())}</span>
+            <input
+                type="range"
+                min="0"
+                max=move || duration().to_string()
+                step="1"
+                aria-label="Seek"
+                aria-valuetext=move || format!("{} of {}", format_ms(value()), format_ms(duration()))
+                disabled=move || !can_seek()
+                prop:value=move || value().to_string()
+                on:input=move |ev| drag.input(&event_target_value(&ev))
+                on:change=move |ev| drag.change(&event_target_value(&ev), |position_ms| position_ms.max(0) as u32)
+            />
+            <span class="spotify-seek-bar-duration">{move || format_ms(duration())}</span>
+        </div>
+    }
+}
+
+/// A range input for the local volume, from 0 to 100, the volume is set when the slider is released
+#[component]
+pub fn VolumeSlider(#[prop(optional, into)] class: Option<String>) -> impl IntoView {
+    let volume = use_volume();
+    let drag = RangeDrag::new(command_action(|volume: &f32| Command::SetVolume(*volume)));
+    let percent = move || drag.value(|| (volume.get().unwrap_or(1.0) * 100.0).round() as i32);
+
+    view! {
+        <input
+            class=class_name("spotify-volume-slider", class)
+            type="range"
+            min="0"
+            max="100"
+            aria-label="Volume"
+            aria-valuetext=move || format!("{}%", percent())
+            disabled=move || volume.get().is_none()
+            prop:value=move || percent().to_string()
+            on:input=move |ev| drag.input(&event_target_value(&ev))
+            on:change=move |ev| drag.change(&event_target_value(&ev), |percent| percent as f32 / 100.0)
+        />
+    }
+}
+
+/// The next tracks of `track_window.next_tracks`
+#[component]
+pub fn QueuePreview(
+    /// Show at most this many tracks
+    #[prop(default = 3)]
+    limit: usize,
+    #[prop(optional, into)] class: Option<String>,
+) -> impl IntoView {
+    let state = use_player_state();
+    let tracks = move || {
+        state.with(|state| {
+            state
+                .as_ref()
+                .map(|state| {
+                    state
+                        .track_window
+                        .next_tracks
+                        .iter()
+                        .take(limit)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        })
+    };
+
+    view! {
+        <ol class=class_name("spotify-queue-preview", class) aria-label="Up next">
+            {move || {
+                tracks()
+                    .into_iter()
+                    .map(|track| {
+                        let artists = track
+                            .artists
+                            .iter()
+                            .map(|artist| artist.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        view! {
+                            <li class="spotify-queue-track">
+                                <span class="spotify-queue-track-title">{track.name.clone()}</span>
+                                <span class="spotify-queue-track-artists">{artists}</span>
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </ol>
+    }
+}
@@ -0,0 +1,56 @@
+// the class has to extend HTMLElement, which can't be done from Rust, so it only forwards to the Rust callbacks
+function define(tag, connected, disconnected, attributeChanged, call) {
+    if (customElements.get(tag)) {
+        return false;
+    }
+    customElements.define(tag, class extends HTMLElement {
+        static get observedAttributes() {
+            return ["name", "volume"];
+        }
+
+        connectedCallback() {
+            connected(this);
+        }
+
+        disconnectedCallback() {
+            disconnected(this);
+        }
+
+        attributeChangedCallback(name, oldValue, newValue) {
+            if (oldValue !== newValue) {
+                attributeChanged(name, newValue);
+            }
+        }
+
+        pause() {
+            return call("pause");
+        }
+
+        resume() {
+            return call("resume");
+        }
+
+        togglePlay() {
+            return call("togglePlay");
+        }
+
+        seek(positionMs) {
+            return call("seek", positionMs);
+        }
+
+        next() {
+            return call("next");
+        }
+
+        previous() {
+            return call("previous");
+        }
+
+        setVolume(volume) {
+            return call("setVolume", volume);
+        }
+    });
+    return true;
+}
+
+export { define };
@@ -0,0 +1,247 @@
+//! A `<spotify-player>` custom element for pages that don't use Rust for their UI, requires the `custom_element` feature.
+//! Call [`define`] once from your wasm module, then use the element from plain HTML or any framework.
+//!
+//! The element reads these attributes:
+//! * `token-endpoint` - A URL that returns JSON with `access_token` and `expires_in`, see [`TokenCache::from_endpoint`]. Required.
+//! * `name` - The name of the player, "Web Player" if it's missing.
+//! * `volume` - The initial volume between 0 and 1.
+//!
+//! It dispatches a `CustomEvent` for every SDK event, with the same name: `ready`, `not_ready`, `player_state_changed`,
+//! `autoplay_failed` and the error events. The detail of `player_state_changed` is the [`StateChange`], `null` when playback moved away.
+//! Its methods `pause()`, `resume()`, `togglePlay()`, `seek(positionMs)`, `next()`, `previous()` and `setVolume(volume)`
+//! return a Promise that rejects with the error message.
+//!
+//! # Example
+//! ```html
+//! <spotify-player name="My site" volume="0.5" token-endpoint="/api/spotify-token"></spotify-player>
+//! <script type="module">
+//!     const player = document.querySelector("spotify-player");
+//!     player.addEventListener("player_state_changed", event => console.log(event.detail));
+//!     document.querySelector("#next").onclick = () => player.next();
+//! </script>
+//! ```
+use std::cell::{Cell, RefCell};
+
+use js_sys::Promise;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::{CustomEvent, CustomEventInit, HtmlElement};
+
+use crate::{
+    backend::SdkEvent,
+    command_queue::Command,
+    error::SdkError,
+    store::{self, player_store, Lifecycle},
+    token::TokenCache,
+    InitOptions,
+};
+
+/// The tag name [`define`] registers
+pub const TAG_NAME: &str = "spotify-player";
+
+/// The SDK events that are dispatched on the element
+const FORWARDED_EVENTS: [&str; 8] = [
+    "ready",
+    "not_ready",
+    "player_state_changed",
+    "autoplay_failed",
+    "initialization_error",
+    "authentication_error",
+    "account_error",
+    "playback_error",
+];
+
+#[wasm_bindgen(module = "/src/custom_element.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = define)]
+    fn define_element(
+        tag: &str,
+        connected: &Closure<dyn FnMut(HtmlElement)>,
+        disconnected: &Closure<dyn FnMut(HtmlElement)>,
+        attribute_changed: &Closure<dyn FnMut(String, Option<String>)>,
+        call: &Closure<dyn FnMut(String, JsValue) -> Promise>,
+    ) -> bool;
+}
+
+thread_local! {
+    static ELEMENTS: RefCell<Vec<HtmlElement>> = const { RefCell::new(Vec::new()) };
+    static LISTENING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Registers the `<spotify-player>` element.
+///
+/// # Response
+/// false if an element with that name was already registered, then nothing changes
+pub fn define() -> bool {
+    let connected = Closure::new(connected);
+    let disconnected = Closure::new(|element: HtmlElement| {
+        ELEMENTS.with(|elements| elements.borrow_mut().retain(|other| *other != element));
+    });
+    let attribute_changed = Closure::new(attribute_changed);
+    let call = Closure::new(call);
+    let defined = define_element(
+        TAG_NAME,
+        &connected,
+        &disconnected,
+        &attribute_changed,
+        &call,
+    );
+    if defined {
+        //the element class keeps calling these, so they can't be cleaned up
+        connected.forget();
+        disconnected.forget();
+        attribute_changed.forget();
+        call.forget();
+    }
+    defined
+}
+
+/// Initializes the player with the attributes of the first element, the others share it
+fn connected(element: HtmlElement) {
+    ELEMENTS.with(|elements| elements.borrow_mut().push(element.clone()));
+    if player_store().get().lifecycle != Lifecycle::Uninitialized {
+        // someone else initialized the player, it may be ready already
+        if crate::player_ready() {
+            listen();
+        }
+        return;
+    }
+    let Some(url) = element.get_attribute("token-endpoint") else {
+        dispatch_to(
+            &element,
+            "initialization_error",
+            &error_detail("the token-endpoint attribute is missing"),
+        );
+        return;
+    };
+    let mut options = InitOptions::new(
+        &element
+            .get_attribute("name")
+            .unwrap_or_else(|| "Web Player".into()),
+    );
+    if let Some(volume) = element
+        .get_attribute("volume")
+        .and_then(|volume| volume.parse().ok())
+    {
+        options.volume = volume;
+    }
+
+    let cache = TokenCache::from_endpoint(&url);
+    // an element connected while the token loads shares this player instead of initializing another one
+    store::on_lifecycle(Lifecycle::Initializing);
+    wasm_bindgen_futures::spawn_local(async move {
+        // the SDK asks for a token right away, so the first one has to be there before init
+        if let Err(error) = cache.refresh().await {
+            store::on_lifecycle(Lifecycle::Uninitialized);
+            dispatch("authentication_error", &error_detail(&error.to_string()));
+            return;
+        }
+        crate::init_with_options(
+            cache.provider(),
+            || {
+                listen();
+                wasm_bindgen_futures::spawn_local(async {
+                    let _ = crate::connect().await;
+                });
+            },
+            options,
+        );
+    });
+}
+
+fn attribute_changed(name: String, value: Option<String>) {
+    if !crate::player_ready() {
+        return;
+    }
+    match (name.as_str(), value) {
+        ("name", Some(name)) => wasm_bindgen_futures::spawn_local(async move {
+            let _ = crate::set_name(name).await;
+        }),
+        ("volume", Some(volume)) => {
+            if let Ok(volume) = volume.parse() {
+                wasm_bindgen_futures::spawn_local(async move {
+                    let _ = player_store().send(Command::SetVolume(volume)).await;
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Runs a method of the element
+fn call(method: String, argument: JsValue) -> Promise {
+    let command = match method.as_str() {
+        "pause" => Ok(Command::Pause),
+        "resume" => Ok(Command::Resume),
+        "togglePlay" => Ok(Command::TogglePlay),
+        "next" => Ok(Command::NextTrack),
+        "previous" => Ok(Command::PreviousTrack),
+        "seek" => argument
+            .as_f64()
+            .map(|position_ms| Command::Seek(position_ms.max(0.0) as u32))
+            .ok_or("seek needs the position in milliseconds"),
+        "setVolume" => argument
+            .as_f64()
+            .map(|volume| Command::SetVolume(volume as f32))
+            .ok_or("setVolume needs a volume between 0 and 1"),
+        _ => Err("unknown method"),
+    };
+    wasm_bindgen_futures::future_to_promise(async move {
+        let command = command.map_err(JsValue::from)?;
+        player_store()
+            .send(command)
+            .await
+            .map(|_| JsValue::UNDEFINED)
+            .map_err(|error: SdkError| JsValue::from(error.to_string()))
+    })
+}
+
+/// Adds the SDK listeners that dispatch to the elements, once
+fn listen() {
+    if LISTENING.with(|listening| listening.replace(true)) {
+        return;
+    }
+    for event in FORWARDED_EVENTS {
+        crate::listen(event, move |sdk_event: SdkEvent| {
+            dispatch(event, &detail(&sdk_event))
+        });
+    }
+}
+
+/// The payload of the event as the SDK passes it, json compatible so `None` is `null` and not `undefined`
+fn detail(event: &SdkEvent) -> JsValue {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    match event {
+        SdkEvent::Ready(player) | SdkEvent::NotReady(player) => player.serialize(&serializer),
+        SdkEvent::PlayerStateChanged(state) => state.serialize(&serializer),
+        SdkEvent::AutoplayFailed => Ok(JsValue::NULL),
+        SdkEvent::InitializationError(error)
+        | SdkEvent::AuthenticationError(error)
+        | SdkEvent::AccountError(error)
+        | SdkEvent::PlaybackError(error) => error.serialize(&serializer),
+    }
+    .unwrap_or(JsValue::NULL)
+}
+
+/// The detail of the error events, the same shape as the SDK's
+fn error_detail(message: &str) -> JsValue {
+    serde_wasm_bindgen::to_value(&crate::structs::web_playback::Error {
+        message: message.into(),
+    })
+    .unwrap_or(JsValue::NULL)
+}
+
+fn dispatch(event: &str, detail: &JsValue) {
+    let elements = ELEMENTS.with(|elements| elements.borrow().clone());
+    for element in elements {
+        dispatch_to(&element, event, detail);
+    }
+}
+
+fn dispatch_to(element: &HtmlElement, event: &str, detail: &JsValue) {
+    let init = CustomEventInit::new();
+    init.set_detail(detail);
+    if let Ok(event) = CustomEvent::new_with_event_init_dict(event, &init) {
+        let _ = element.dispatch_event(&event);
+    }
+}
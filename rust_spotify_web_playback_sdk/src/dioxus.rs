@@ -0,0 +1,199 @@
+//! Dioxus bindings, requires the `dioxus` feature.
+//! [`use_spotify_player`] initializes the player once and feeds signals from the [`PlayerStore`](crate::store::PlayerStore),
+//! the store subscription and the tasks that feed the signals are dropped with the component.
+//!
+//! # Example
+//! ```rust,ignore
+//! use dioxus::prelude::*;
+//! use rust_spotify_web_playback_sdk::dioxus::*;
+//!
+//! #[component]
+//! fn Player() -> Element {
+//!     let player = use_spotify_player(|| SpotifyOptions::new("example player", || "token".to_string()));
+//!     let track = player.state.read().as_ref().map(|state| state.track_window.current_track.name.clone());
+//!     rsx! {
+//!         p { {track} }
+//!         button { onclick: move |_| player.controls.toggle_play.call(()), "play/pause" }
+//!     }
+//! }
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use ::dioxus::prelude::{
+    spawn, use_drop, use_effect, use_hook, use_signal, Callback, ReadOnlySignal, Writable,
+};
+use futures::{channel::mpsc, StreamExt};
+
+use crate::{
+    command_queue::Command,
+    store::{player_store, Anchor, Lifecycle, SubscriptionId, POSITION_INTERVAL_MS},
+    structs::state_change::StateChange,
+    InitOptions,
+};
+
+/// The options of [`use_spotify_player`], they are only used by the first component that initializes the player
+pub struct SpotifyOptions {
+    oauth: Box<dyn FnMut() -> String>,
+    /// The options passed to [`init_with_options`](crate::init_with_options).
+    pub init: InitOptions,
+    /// Call [`connect`](crate::connect) once the SDK is ready, true by default.
+    pub connect: bool,
+}
+
+impl SpotifyOptions {
+    /// The default options with the given player name
+    ///
+    /// # Arguments
+    /// * `name` - The name of the player.
+    /// * `oauth` - A closure that returns a String containing the Spotify OAuth token.
+    pub fn new<T>(name: &str, oauth: T) -> Self
+    where
+        T: FnMut() -> String + 'static,
+    {
+        SpotifyOptions {
+            oauth: Box::new(oauth),
+            init: InitOptions::new(name),
+            connect: true,
+        }
+    }
+}
+
+/// The signals and controls [`use_spotify_player`] returns
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpotifyPlayer {
+    /// The latest state, with the optimistic changes of the controls applied
+    pub state: ReadOnlySignal<Option<StateChange>>,
+    pub lifecycle: ReadOnlySignal<Lifecycle>,
+    pub volume: ReadOnlySignal<Option<f32>>,
+    pub device_id: ReadOnlySignal<Option<String>>,
+    /// The position in milliseconds, moving forward between state changes while playing
+    pub position: ReadOnlySignal<i32>,
+    pub controls: SpotifyControls,
+}
+
+/// Callbacks that send commands through the [`PlayerStore`](crate::store::PlayerStore), so the state updates right away.
+/// A command that fails is rolled back and reported with [`Event::OptimisticRollback`](crate::events::Event::OptimisticRollback).
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpotifyControls {
+    pub pause: Callback<()>,
+    pub resume: Callback<()>,
+    pub toggle_play: Callback<()>,
+    pub next_track: Callback<()>,
+    pub previous_track: Callback<()>,
+    /// Seek to the position in milliseconds
+    pub seek: Callback<u32>,
+    /// Set the volume between 0 and 1
+    pub set_volume: Callback<f32>,
+    /// See [`activate_element`](crate::activate_element), call it from a click handler
+    pub activate_element: Callback<()>,
+}
+
+/// Initializes the player if it wasn't yet, and returns signals that follow it.
+/// The SDK is only touched in an effect, so it's safe to call it during server side rendering.
+///
+/// # Arguments
+/// * `options` - Creates the options, only called on the first render.
+pub fn use_spotify_player(options: impl FnOnce() -> SpotifyOptions) -> SpotifyPlayer {
+    let initial = use_hook(|| Rc::new(player_store().get()));
+    let mut state = use_signal(|| initial.state.clone());
+    let mut lifecycle = use_signal(|| initial.lifecycle);
+    let mut volume = use_signal(|| initial.volume);
+    let mut device_id = use_signal(|| initial.device_id.clone());
+    let mut position = use_signal(|| initial.position().unwrap_or_default());
+    let options = use_hook(|| Rc::new(RefCell::new(Some(options()))));
+    let subscription: Rc<Cell<Option<SubscriptionId>>> = use_hook(|| Rc::new(Cell::new(None)));
+
+    // effects don't run on the server
+    use_effect({
+        let subscription = subscription.clone();
+        move || {
+            let Some(options) = options.borrow_mut().take() else {
+                return;
+            };
+            let store = player_store();
+            let anchor = Rc::new(Cell::new(Anchor::from_state(&store.get().state)));
+
+            // the store calls its subscribers outside of the Dioxus runtime, so the signals are written by a task of the component
+            let (sender, mut receiver) = mpsc::unbounded();
+            subscription.set(Some(store.subscribe(move |snapshot| {
+                let _ = sender.unbounded_send(snapshot.clone());
+            })));
+            spawn({
+                let anchor = anchor.clone();
+                async move {
+                    while let Some(snapshot) = receiver.next().await {
+                        anchor.set(Anchor::from_state(&snapshot.state));
+                        position.set(anchor.get().position());
+                        state.set(snapshot.state);
+                        lifecycle.set(snapshot.lifecycle);
+                        volume.set(snapshot.volume);
+                        device_id.set(snapshot.device_id);
+                    }
+                }
+            });
+            spawn(async move {
+                loop {
+                    crate::sleep(POSITION_INTERVAL_MS).await;
+                    let anchor = anchor.get();
+                    if anchor.playing {
+                        position.set(anchor.position());
+                    }
+                }
+            });
+
+            if store.get().lifecycle == Lifecycle::Uninitialized {
+                let connect = options.connect;
+                crate::init_with_options(
+                    options.oauth,
+                    move || {
+                        if connect {
+                            wasm_bindgen_futures::spawn_local(async {
+                                let _ = crate::connect().await;
+                            });
+                        }
+                    },
+                    options.init,
+                );
+            }
+        }
+    });
+    use_drop(move || {
+        if let Some(subscription) = subscription.take() {
+            player_store().unsubscribe(subscription);
+        }
+    });
+
+    let controls = use_hook(|| SpotifyControls {
+        pause: command_callback(|_| Command::Pause),
+        resume: command_callback(|_| Command::Resume),
+        toggle_play: command_callback(|_| Command::TogglePlay),
+        next_track: command_callback(|_| Command::NextTrack),
+        previous_track: command_callback(|_| Command::PreviousTrack),
+        seek: command_callback(Command::Seek),
+        set_volume: command_callback(Command::SetVolume),
+        activate_element: Callback::new(|_| async {
+            let _ = crate::activate_element().await;
+        }),
+    });
+
+    SpotifyPlayer {
+        state: state.into(),
+        lifecycle: lifecycle.into(),
+        volume: volume.into(),
+        device_id: device_id.into(),
+        position: position.into(),
+        controls,
+    }
+}
+
+fn command_callback<T: 'static>(command: impl Fn(T) -> Command + 'static) -> Callback<T> {
+    Callback::new(move |input| {
+        let command = command(input);
+        async move {
+            let _ = player_store().send(command).await;
+        }
+    })
+}
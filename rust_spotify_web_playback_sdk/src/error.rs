@@ -0,0 +1,188 @@
+use std::fmt;
+
+use crate::restrictions::Action;
+
+/// The error type of the crate, the Web Playback SDK commands and the parts that talk to Spotify outside of it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdkError {
+    /// `init` was not called yet, or the SDK script has not finished loading
+    PlayerNotReady,
+    /// The SDK only exists in the browser, this was called on a native target like a server
+    NotInBrowser,
+    /// The Web API answered with a non success status code
+    Api {
+        status: u16,
+        message: String,
+        /// The `reason` field Spotify adds to some player errors, like `NO_ACTIVE_DEVICE`
+        reason: Option<String>,
+    },
+    /// The request never got a response (network error, CORS, invalid url...)
+    Request(String),
+    /// The response body could not be deserialized
+    Deserialize(String),
+    /// The login flow failed, or its state is missing from the storage
+    Auth(String),
+    /// The Web Playback SDK rejected a command
+    Command(String),
+    /// The player did not reach the expected state in time
+    Timeout,
+    /// The player reported a state that doesn't show the change of the command
+    Unconfirmed,
+    /// A newer command superseded the command, or its queue was cleared
+    Cancelled,
+    /// There is no playback on this device, so there is no state to act on
+    NoPlayback,
+    /// The arguments can't make a valid request, like [`play`](crate::play) with no tracks
+    InvalidRequest(String),
+    /// The current state doesn't allow the action, for example toggling shuffle during an ad
+    Disallowed {
+        action: Action,
+        /// The `disallow_*_reasons` of the state's restrictions, can be empty
+        reasons: Vec<String>,
+    },
+}
+
+impl fmt::Display for SdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SdkError::PlayerNotReady => write!(f, "player not ready"),
+            SdkError::NotInBrowser => write!(f, "not running in a browser"),
+            SdkError::Api {
+                status,
+                message,
+                reason: Some(reason),
+            } => write!(f, "web api error {status}: {message} ({reason})"),
+            SdkError::Api {
+                status,
+                message,
+                reason: None,
+            } => write!(f, "web api error {status}: {message}"),
+            SdkError::Request(e) => write!(f, "request failed: {e}"),
+            SdkError::Deserialize(e) => write!(f, "could not deserialize response: {e}"),
+            SdkError::Auth(e) => write!(f, "authorization failed: {e}"),
+            SdkError::Command(e) => write!(f, "player command failed: {e}"),
+            SdkError::Timeout => write!(f, "timed out waiting for the player state"),
+            SdkError::Unconfirmed => write!(f, "the player state doesn't show the command"),
+            SdkError::Cancelled => write!(f, "the command was cancelled"),
+            SdkError::NoPlayback => write!(f, "there is no playback on this device"),
+            SdkError::InvalidRequest(e) => write!(f, "invalid request: {e}"),
+            SdkError::Disallowed { action, reasons } if reasons.is_empty() => {
+                write!(f, "{action:?} is not allowed right now")
+            }
+            SdkError::Disallowed { action, reasons } => {
+                write!(
+                    f,
+                    "{action:?} is not allowed right now: {}",
+                    reasons.join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SdkError {}
+
+/// What went wrong, according to the event and the message of a [`structs::web_playback::Error`](crate::structs::web_playback::Error)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `initialization_error`: the browser doesn't support Encrypted Media Extensions
+    UnsupportedBrowser,
+    /// `initialization_error` for any other reason
+    InitializationFailed,
+    /// `authentication_error`: the token is invalid or expired
+    InvalidToken,
+    /// `authentication_error`: the token doesn't have the scopes in [`SCOPES`](crate::SCOPES)
+    MissingScopes,
+    /// `account_error`: the account doesn't have Spotify Premium
+    PremiumRequired,
+    /// `playback_error`: the track could not be loaded or played
+    PlaybackFailed,
+    /// `playback_error`: a command was sent while nothing is loaded on this device
+    NothingLoaded,
+    /// Not one of the known messages
+    Unknown,
+}
+
+/// What the user or the app can do about an [`ErrorKind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Recovery {
+    /// Use a browser that supports Encrypted Media Extensions
+    SwitchBrowser,
+    /// Reload the page, or call `init` again
+    Reload,
+    /// Log in again to get a new token, with all the scopes
+    Reauthenticate,
+    /// Upgrade the account to Spotify Premium
+    UpgradeToPremium,
+    /// Try the command again, or skip to the next track
+    Retry,
+    /// Transfer playback to this device or start playing something
+    StartPlayback,
+    /// Nothing known helps
+    None,
+}
+
+/// The `initialization_error` of browsers without Encrypted Media Extensions
+const EME_NOT_SUPPORTED: &str = "Failed to initialize player: EME not supported";
+/// The `authentication_error` of a token without the scopes the SDK needs
+const INVALID_SCOPES: &str = "Invalid token scopes.";
+/// The `playback_error` of a command sent before anything was played on the device
+const NO_LIST_LOADED: &str = "Cannot perform operation; no list was loaded.";
+
+impl ErrorKind {
+    /// Classifies an error event, telling errors of the same event apart by the exact messages the SDK sends.
+    /// Messages that aren't known get the usual kind of the event.
+    ///
+    /// # Arguments
+    /// * `event` - The event the error came from, like `"authentication_error"`.
+    /// * `message` - The `message` of the error.
+    pub fn classify(event: &str, message: &str) -> Self {
+        let is = |known: &str| message.trim().eq_ignore_ascii_case(known);
+        match event {
+            "initialization_error" if is(EME_NOT_SUPPORTED) => ErrorKind::UnsupportedBrowser,
+            "initialization_error" => ErrorKind::InitializationFailed,
+            "authentication_error" if is(INVALID_SCOPES) => ErrorKind::MissingScopes,
+            "authentication_error" => ErrorKind::InvalidToken,
+            "account_error" => ErrorKind::PremiumRequired,
+            "playback_error" if is(NO_LIST_LOADED) => ErrorKind::NothingLoaded,
+            "playback_error" => ErrorKind::PlaybackFailed,
+            _ => ErrorKind::Unknown,
+        }
+    }
+
+    /// True if the player can work again without changing the browser or the account
+    pub fn recoverable(&self) -> bool {
+        !matches!(
+            self,
+            ErrorKind::UnsupportedBrowser | ErrorKind::PremiumRequired | ErrorKind::Unknown
+        )
+    }
+
+    /// The suggested way to recover
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            ErrorKind::UnsupportedBrowser => Recovery::SwitchBrowser,
+            ErrorKind::InitializationFailed => Recovery::Reload,
+            ErrorKind::InvalidToken | ErrorKind::MissingScopes => Recovery::Reauthenticate,
+            ErrorKind::PremiumRequired => Recovery::UpgradeToPremium,
+            ErrorKind::PlaybackFailed => Recovery::Retry,
+            ErrorKind::NothingLoaded => Recovery::StartPlayback,
+            ErrorKind::Unknown => Recovery::None,
+        }
+    }
+}
+
+impl Recovery {
+    /// A short message that can be shown to the user
+    pub fn message(&self) -> &'static str {
+        match self {
+            Recovery::SwitchBrowser => "This browser can't play Spotify, please try another one",
+            Recovery::Reload => "Please reload the page",
+            Recovery::Reauthenticate => "Please log in again",
+            Recovery::UpgradeToPremium => "Please upgrade to Spotify Premium",
+            Recovery::Retry => "Please try again",
+            Recovery::StartPlayback => "Please start playing something on this device",
+            Recovery::None => "Something went wrong",
+        }
+    }
+}
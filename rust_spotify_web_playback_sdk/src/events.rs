@@ -0,0 +1,110 @@
+//! Events emitted by the crate itself, on top of the ones the Web Playback SDK emits.
+//! For the SDK events use the `add_listener!` macro.
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    command_queue::Command,
+    error::SdkError,
+    reconnect::ReconnectReason,
+    watchdog::{RecoveryStep, StallReason},
+};
+
+/// An event emitted by the crate
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// Playback was transferred to this device after the `ready` event, see `InitOptions::auto_activate`
+    #[cfg(feature = "web_api")]
+    DeviceActivated { device_id: String },
+    /// Playback could not be transferred to this device after the `ready` event
+    #[cfg(feature = "web_api")]
+    ActivationFailed { device_id: String, error: SdkError },
+    /// The token cache got a new token, `expires_at` is in milliseconds since the unix epoch
+    TokenRefreshed { expires_at: u64 },
+    /// The token cache could not get a new token, the SDK keeps the old one until it expires
+    TokenRefreshFailed { error: SdkError },
+    /// The reconnect supervisor will call `connect` after `delay_ms`
+    ReconnectAttempt {
+        attempt: u32,
+        delay_ms: u32,
+        reason: ReconnectReason,
+    },
+    /// The reconnect supervisor connected the player again
+    Reconnected { attempts: u32 },
+    /// The reconnect supervisor stopped trying after `attempts` failed attempts
+    ReconnectGaveUp { attempts: u32 },
+    /// The watchdog noticed that playback is stuck at `position_ms`
+    PlaybackStalled {
+        reason: StallReason,
+        position_ms: i32,
+    },
+    /// The watchdog is running a recovery step after a stall
+    StallRecovery { step: RecoveryStep },
+    /// The player store undid the optimistic change of `command`, because it failed, a state contradicted it or it wasn't confirmed in time
+    OptimisticRollback { command: Command, error: SdkError },
+}
+
+/// Identifies a listener added with [`add_event_listener`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u32);
+
+type Listener = Rc<RefCell<dyn FnMut(&Event)>>;
+
+thread_local! {
+    static LISTENERS: RefCell<Vec<(ListenerId, Listener)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: RefCell<u32> = const { RefCell::new(0) };
+}
+
+/// Add a listener for the events emitted by the crate.
+///
+/// # Response
+/// Returns the id of the listener, pass it to [`remove_event_listener`] to remove it.
+///
+/// # Arguments
+/// * `callback` - Called with every event, in the order they are emitted.
+pub fn add_event_listener<F>(callback: F) -> ListenerId
+where
+    F: FnMut(&Event) + 'static,
+{
+    let id = NEXT_ID.with(|next| {
+        let mut next = next.borrow_mut();
+        *next += 1;
+        ListenerId(*next)
+    });
+    LISTENERS.with(|listeners| {
+        listeners
+            .borrow_mut()
+            .push((id, Rc::new(RefCell::new(callback))))
+    });
+    id
+}
+
+/// Remove a listener added with [`add_event_listener`].
+///
+/// # Response
+/// Returns true if the listener was registered.
+pub fn remove_event_listener(id: ListenerId) -> bool {
+    LISTENERS.with(|listeners| {
+        let mut listeners = listeners.borrow_mut();
+        let len = listeners.len();
+        listeners.retain(|(listener_id, _)| *listener_id != id);
+        listeners.len() != len
+    })
+}
+
+/// Calls every listener with `event`, listeners can add and remove listeners while they are called
+pub(crate) fn emit(event: Event) {
+    let listeners: Vec<Listener> = LISTENERS.with(|listeners| {
+        listeners
+            .borrow()
+            .iter()
+            .map(|(_, listener)| listener.clone())
+            .collect()
+    });
+    for listener in listeners {
+        // a listener that emits an event itself is not called recursively
+        if let Ok(mut listener) = listener.try_borrow_mut() {
+            listener(&event);
+        }
+    }
+}
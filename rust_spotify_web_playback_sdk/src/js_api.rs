@@ -0,0 +1,352 @@
+//! The crate's typed API exported back to JavaScript for apps that mix TypeScript and Rust, requires the `js-api` feature.
+//! `StateChange`, `State` and `Track` get TypeScript definitions, so the TS side gets the same validated data as the Rust side.
+//!
+//! # Example
+//! ```ts
+//! import init, { RustSpotifyPlayer, type StateChange } from "./pkg/my_app";
+//!
+//! await init();
+//! const player = new RustSpotifyPlayer("My app", () => getToken(), 0.5);
+//! const unsubscribe = player.subscribe((state?: StateChange) => console.log(state?.track_window.current_track.name));
+//! await player.pauseConfirmed();
+//! ```
+use std::{cell::RefCell, future::Future, rc::Rc};
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    command_queue::{Command, CommandQueue},
+    error::SdkError,
+    reconnect::{ReconnectOptions, Supervisor},
+    store::{player_store, Lifecycle, SubscriptionId},
+    structs::state_change::StateChange,
+    InitOptions, STATE_TIMEOUT_MS,
+};
+
+/// A handle on the player for JavaScript, every instance shares the one Spotify player
+#[wasm_bindgen]
+pub struct RustSpotifyPlayer {
+    queue: CommandQueue,
+    supervisor: RefCell<Option<Supervisor>>,
+    /// Removed when their unsubscribe function is called, or when the instance is freed
+    subscriptions: Rc<RefCell<Vec<SubscriptionId>>>,
+}
+
+#[wasm_bindgen]
+impl RustSpotifyPlayer {
+    /// Initializes the player if it wasn't yet, and connects it once the SDK is ready.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the player.
+    /// * `get_oauth_token` - Returns the Spotify OAuth token.
+    /// * `volume` - The initial volume between 0 and 1, 1 if it's missing.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        name: String,
+        #[wasm_bindgen(js_name = getOAuthToken, unchecked_param_type = "() => string")]
+        get_oauth_token: Function,
+        volume: Option<f32>,
+    ) -> RustSpotifyPlayer {
+        if player_store().get().lifecycle == Lifecycle::Uninitialized {
+            let mut options = InitOptions::new(&name);
+            if let Some(volume) = volume {
+                options.volume = volume;
+            }
+            crate::init_with_options(
+                move || {
+                    get_oauth_token
+                        .call0(&JsValue::NULL)
+                        .ok()
+                        .and_then(|token| token.as_string())
+                        .unwrap_or_default()
+                },
+                || {
+                    wasm_bindgen_futures::spawn_local(async {
+                        let _ = crate::connect().await;
+                    });
+                },
+                options,
+            );
+        }
+        RustSpotifyPlayer {
+            queue: CommandQueue::new(),
+            supervisor: RefCell::new(None),
+            subscriptions: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// The latest state of the store, with the optimistic changes applied
+    pub fn state(&self) -> Option<StateChange> {
+        player_store().state()
+    }
+
+    /// Asks the SDK for the current state
+    #[wasm_bindgen(js_name = currentState, unchecked_return_type = "Promise<State | undefined>")]
+    pub fn current_state(&self) -> Promise {
+        promise(async {
+            crate::get_current_state()
+                .await
+                .map(|state| state.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// One of `uninitialized`, `initializing`, `ready`, `not_ready` and `failed`
+    pub fn lifecycle(&self) -> String {
+        match player_store().get().lifecycle {
+            Lifecycle::Uninitialized => "uninitialized",
+            Lifecycle::Initializing => "initializing",
+            Lifecycle::Ready => "ready",
+            Lifecycle::NotReady => "not_ready",
+            Lifecycle::Failed(_) => "failed",
+        }
+        .into()
+    }
+
+    #[wasm_bindgen(js_name = deviceId)]
+    pub fn device_id(&self) -> Option<String> {
+        player_store().get().device_id
+    }
+
+    /// The local volume, `undefined` before the player is initialized
+    pub fn volume(&self) -> Option<f32> {
+        player_store().get().volume
+    }
+
+    /// True while a command sent through the store waits for its confirmation
+    #[wasm_bindgen(js_name = isPending)]
+    pub fn is_pending(&self) -> bool {
+        player_store().is_pending()
+    }
+
+    /// Calls `callback` with the state every time the store changes.
+    ///
+    /// # Response
+    /// Returns the function that removes the callback. Freeing the player removes it too.
+    #[wasm_bindgen(unchecked_return_type = "() => void")]
+    pub fn subscribe(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "(state: StateChange | undefined) => void")]
+        callback: Function,
+    ) -> JsValue {
+        let subscription = player_store().subscribe(move |snapshot| {
+            let state = snapshot
+                .state
+                .clone()
+                .map(JsValue::from)
+                .unwrap_or(JsValue::UNDEFINED);
+            let _ = callback.call1(&JsValue::NULL, &state);
+        });
+        self.subscriptions.borrow_mut().push(subscription);
+        let subscriptions = Rc::downgrade(&self.subscriptions);
+        Closure::<dyn FnMut()>::new(move || {
+            if let Some(subscriptions) = subscriptions.upgrade() {
+                subscriptions
+                    .borrow_mut()
+                    .retain(|other| *other != subscription);
+            }
+            player_store().unsubscribe(subscription);
+        })
+        .into_js_value()
+    }
+
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn pause(&self) -> Promise {
+        send(Command::Pause)
+    }
+
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn resume(&self) -> Promise {
+        send(Command::Resume)
+    }
+
+    #[wasm_bindgen(js_name = togglePlay, unchecked_return_type = "Promise<void>")]
+    pub fn toggle_play(&self) -> Promise {
+        send(Command::TogglePlay)
+    }
+
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn seek(&self, #[wasm_bindgen(js_name = positionMs)] position_ms: u32) -> Promise {
+        send(Command::Seek(position_ms))
+    }
+
+    #[wasm_bindgen(js_name = nextTrack, unchecked_return_type = "Promise<void>")]
+    pub fn next_track(&self) -> Promise {
+        send(Command::NextTrack)
+    }
+
+    #[wasm_bindgen(js_name = previousTrack, unchecked_return_type = "Promise<void>")]
+    pub fn previous_track(&self) -> Promise {
+        send(Command::PreviousTrack)
+    }
+
+    #[wasm_bindgen(js_name = setVolume, unchecked_return_type = "Promise<void>")]
+    pub fn set_volume(&self, volume: f32) -> Promise {
+        send(Command::SetVolume(volume))
+    }
+
+    /// Pauses and resolves with the state change that shows it, `timeoutMs` is 10 seconds by default
+    #[wasm_bindgen(js_name = pauseConfirmed, unchecked_return_type = "Promise<StateChange>")]
+    pub fn pause_confirmed(
+        &self,
+        #[wasm_bindgen(js_name = timeoutMs)] timeout_ms: Option<u32>,
+    ) -> Promise {
+        promise(crate::pause_confirmed(
+            timeout_ms.unwrap_or(STATE_TIMEOUT_MS),
+        ))
+    }
+
+    /// Resumes and resolves with the state change that shows it, `timeoutMs` is 10 seconds by default
+    #[wasm_bindgen(js_name = resumeConfirmed, unchecked_return_type = "Promise<StateChange>")]
+    pub fn resume_confirmed(
+        &self,
+        #[wasm_bindgen(js_name = timeoutMs)] timeout_ms: Option<u32>,
+    ) -> Promise {
+        promise(crate::resume_confirmed(
+            timeout_ms.unwrap_or(STATE_TIMEOUT_MS),
+        ))
+    }
+
+    /// Seeks and resolves with the state change that shows the new position, `timeoutMs` is 10 seconds by default
+    #[wasm_bindgen(js_name = seekConfirmed, unchecked_return_type = "Promise<StateChange>")]
+    pub fn seek_confirmed(
+        &self,
+        #[wasm_bindgen(js_name = positionMs)] position_ms: u32,
+        #[wasm_bindgen(js_name = timeoutMs)] timeout_ms: Option<u32>,
+    ) -> Promise {
+        promise(crate::seek_confirmed(
+            position_ms,
+            timeout_ms.unwrap_or(STATE_TIMEOUT_MS),
+        ))
+    }
+
+    /// Skips and resolves with the state change that shows another track, `timeoutMs` is 10 seconds by default
+    #[wasm_bindgen(js_name = nextTrackConfirmed, unchecked_return_type = "Promise<StateChange>")]
+    pub fn next_track_confirmed(
+        &self,
+        #[wasm_bindgen(js_name = timeoutMs)] timeout_ms: Option<u32>,
+    ) -> Promise {
+        promise(crate::next_track_confirmed(
+            timeout_ms.unwrap_or(STATE_TIMEOUT_MS),
+        ))
+    }
+
+    /// Starts the reconnect supervisor with the default options, it throws if the player isn't ready
+    #[wasm_bindgen(js_name = startReconnect)]
+    pub fn start_reconnect(&self) -> Result<(), JsError> {
+        if self.supervisor.borrow().is_some() {
+            return Ok(());
+        }
+        let supervisor = crate::reconnect::start(ReconnectOptions::default())?;
+        *self.supervisor.borrow_mut() = Some(supervisor);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = stopReconnect)]
+    pub fn stop_reconnect(&self) {
+        if let Some(supervisor) = self.supervisor.borrow_mut().take() {
+            supervisor.stop();
+        }
+    }
+
+    #[wasm_bindgen(js_name = isReconnecting)]
+    pub fn is_reconnecting(&self) -> bool {
+        self.supervisor
+            .borrow()
+            .as_ref()
+            .is_some_and(Supervisor::is_reconnecting)
+    }
+
+    /// Sends the command through the command queue, after the commands queued before it.
+    /// Queued seeks and volume changes are cancelled by newer ones.
+    /// Rejects without sending anything if the command is unknown, or its value is missing or out of range.
+    ///
+    /// # Arguments
+    /// * `command` - The command to send.
+    /// * `value` - The position in milliseconds for `seek`, the volume for `setVolume`.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn enqueue(
+        &self,
+        #[wasm_bindgen(
+            unchecked_param_type = "\"pause\" | \"resume\" | \"togglePlay\" | \"seek\" | \"setVolume\" | \"nextTrack\" | \"previousTrack\""
+        )]
+        command: String,
+        value: Option<f64>,
+    ) -> Promise {
+        let queue = self.queue.clone();
+        promise(async move {
+            let command = parse_command(&command, value)?;
+            queue.send(command).await.map(|_| JsValue::UNDEFINED)
+        })
+    }
+
+    /// The number of commands waiting in the queue
+    #[wasm_bindgen(js_name = queueLength)]
+    pub fn queue_length(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Cancels the commands waiting in the queue
+    #[wasm_bindgen(js_name = clearQueue)]
+    pub fn clear_queue(&self) {
+        self.queue.clear();
+    }
+}
+
+impl Drop for RustSpotifyPlayer {
+    fn drop(&mut self) {
+        let store = player_store();
+        for subscription in self.subscriptions.borrow_mut().drain(..) {
+            store.unsubscribe(subscription);
+        }
+    }
+}
+
+fn parse_command(command: &str, value: Option<f64>) -> Result<Command, SdkError> {
+    let invalid = |reason: &str| SdkError::InvalidRequest(format!("{command} {reason}"));
+    let value = || match value {
+        Some(value) if value.is_finite() => Ok(value),
+        Some(_) => Err(invalid("needs a finite value")),
+        None => Err(invalid("needs a value")),
+    };
+    Ok(match command {
+        "pause" => Command::Pause,
+        "resume" => Command::Resume,
+        "togglePlay" => Command::TogglePlay,
+        "seek" => Command::Seek(value()?.max(0.0) as u32),
+        "setVolume" => match value()? {
+            volume @ 0.0..=1.0 => Command::SetVolume(volume as f32),
+            _ => return Err(invalid("needs a volume between 0 and 1")),
+        },
+        "nextTrack" => Command::NextTrack,
+        "previousTrack" => Command::PreviousTrack,
+        _ => {
+            return Err(SdkError::InvalidRequest(format!(
+                "unknown command {command}"
+            )))
+        }
+    })
+}
+
+fn send(command: Command) -> Promise {
+    promise(async move {
+        player_store()
+            .send(command)
+            .await
+            .map(|_| JsValue::UNDEFINED)
+    })
+}
+
+/// A Promise that rejects with an `Error` holding the message of the [`SdkError`]
+fn promise<T, F>(future: F) -> Promise
+where
+    T: Into<JsValue>,
+    F: Future<Output = Result<T, SdkError>> + 'static,
+{
+    wasm_bindgen_futures::future_to_promise(async move {
+        future
+            .await
+            .map(Into::into)
+            .map_err(|error| JsError::from(error).into())
+    })
+}
@@ -4,11 +4,16 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen(module = "/src/wrapper.js")]
 extern "C" {
     #[wasm_bindgen]
-    pub fn init(oauth: &Closure<dyn FnMut() -> String>, on_ready: &Closure<dyn FnMut()>, name: String, volume: f32, enable_media_session: bool);
-    
-    /// Check if the player object is ready
-    #[wasm_bindgen]
-    pub fn player_ready() -> bool;
+    pub fn init(
+        oauth: &Closure<dyn FnMut() -> String>,
+        on_ready: &Closure<dyn FnMut()>,
+        name: String,
+        volume: f32,
+        enable_media_session: bool,
+    );
+
+    #[wasm_bindgen(js_name = player_ready)]
+    fn sdk_player_ready() -> bool;
 
     /// Log the player object to the console
     #[wasm_bindgen]
@@ -64,4 +69,13 @@ extern "C" {
 
     #[wasm_bindgen(js_name = activateElement)]
     pub fn activateElement() -> Promise;
-}
\ No newline at end of file
+
+    /// A promise that resolves after `ms` milliseconds
+    #[wasm_bindgen(js_name = sleep)]
+    pub fn sleep(ms: u32) -> Promise;
+}
+
+/// Check if the player object is ready, always false outside of the browser
+pub fn player_ready() -> bool {
+    cfg!(target_arch = "wasm32") && sdk_player_ready()
+}
@@ -0,0 +1,234 @@
+//! Leptos bindings, requires the `leptos` feature.
+//! [`provide_spotify_player`] initializes the player once and feeds signals from the [`PlayerStore`](crate::store::PlayerStore),
+//! the hooks read them from the context.
+//!
+//! # Example
+//! ```rust,ignore
+//! use leptos::*;
+//! use rust_spotify_web_playback_sdk::leptos::*;
+//!
+//! #[component]
+//! fn App() -> impl IntoView {
+//!     provide_spotify_player(SpotifyPlayerOptions::new("example player", || "token".to_string()));
+//!     view! { <NowPlaying/> }
+//! }
+//!
+//! #[component]
+//! fn NowPlaying() -> impl IntoView {
+//!     let track = use_current_track();
+//!     let actions = use_player_actions();
+//!     view! {
+//!         <p>{move || track.get().map(|track| track.name)}</p>
+//!         <button on:click=move |_| actions.toggle_play.dispatch(())>"play/pause"</button>
+//!     }
+//! }
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+use ::leptos::{
+    create_action, create_effect, create_memo, create_signal, expect_context, on_cleanup,
+    provide_context, set_interval_with_handle, Action, Memo, ReadSignal, SignalSet, SignalWith,
+};
+
+use crate::{
+    command_queue::Command,
+    error::SdkError,
+    store::{player_store, Anchor, Lifecycle, POSITION_INTERVAL_MS},
+    structs::{state_change::StateChange, Track},
+    InitOptions,
+};
+
+/// The options of [`provide_spotify_player`]
+pub struct SpotifyPlayerOptions {
+    oauth: Box<dyn FnMut() -> String>,
+    /// The options passed to [`init_with_options`](crate::init_with_options).
+    pub init: InitOptions,
+    /// Call [`connect`](crate::connect) once the SDK is ready, true by default.
+    pub connect: bool,
+}
+
+impl SpotifyPlayerOptions {
+    /// The default options with the given player name
+    ///
+    /// # Arguments
+    /// * `name` - The name of the player.
+    /// * `oauth` - A closure that returns a String containing the Spotify OAuth token.
+    pub fn new<T>(name: &str, oauth: T) -> Self
+    where
+        T: FnMut() -> String + 'static,
+    {
+        SpotifyPlayerOptions {
+            oauth: Box::new(oauth),
+            init: InitOptions::new(name),
+            connect: true,
+        }
+    }
+}
+
+/// The signals [`provide_spotify_player`] puts in the context
+#[derive(Debug, Clone, Copy)]
+pub struct SpotifyPlayer {
+    /// The latest state, with the optimistic changes of the actions applied
+    pub state: ReadSignal<Option<StateChange>>,
+    pub lifecycle: ReadSignal<Lifecycle>,
+    pub volume: ReadSignal<Option<f32>>,
+    pub device_id: ReadSignal<Option<String>>,
+    /// The position in milliseconds, moving forward between state changes while playing
+    pub position: ReadSignal<i32>,
+}
+
+/// Initializes the player if it wasn't yet, and provides its signals to the children of the current component.
+/// The SDK is only touched in an effect, so it's safe to call it during server side rendering and hydration.
+/// The store subscription and the position timer are removed when the component is cleaned up.
+pub fn provide_spotify_player(options: SpotifyPlayerOptions) -> SpotifyPlayer {
+    let snapshot = player_store().get();
+    let (state, set_state) = create_signal(snapshot.state.clone());
+    let (lifecycle, set_lifecycle) = create_signal(snapshot.lifecycle);
+    let (volume, set_volume) = create_signal(snapshot.volume);
+    let (device_id, set_device_id) = create_signal(snapshot.device_id.clone());
+    let (position, set_position) = create_signal(snapshot.position().unwrap_or_default());
+    let player = SpotifyPlayer {
+        state,
+        lifecycle,
+        volume,
+        device_id,
+        position,
+    };
+    provide_context(player);
+
+    let options = RefCell::new(Some(options));
+    // effects don't run on the server
+    create_effect(move |_| {
+        let Some(options) = options.borrow_mut().take() else {
+            return;
+        };
+        let store = player_store();
+        let anchor = Rc::new(Cell::new(Anchor::from_state(&store.get().state)));
+
+        let subscription = store.subscribe({
+            let anchor = anchor.clone();
+            move |snapshot| {
+                anchor.set(Anchor::from_state(&snapshot.state));
+                set_position.set(anchor.get().position());
+                set_state.set(snapshot.state.clone());
+                set_lifecycle.set(snapshot.lifecycle);
+                set_volume.set(snapshot.volume);
+                set_device_id.set(snapshot.device_id.clone());
+            }
+        });
+        let interval = set_interval_with_handle(
+            move || {
+                let anchor = anchor.get();
+                if anchor.playing {
+                    set_position.set(anchor.position());
+                }
+            },
+            Duration::from_millis(u64::from(POSITION_INTERVAL_MS)),
+        )
+        .ok();
+        on_cleanup(move || {
+            player_store().unsubscribe(subscription);
+            if let Some(interval) = interval {
+                interval.clear();
+            }
+        });
+
+        if store.get().lifecycle == Lifecycle::Uninitialized {
+            let connect = options.connect;
+            crate::init_with_options(
+                options.oauth,
+                move || {
+                    if connect {
+                        wasm_bindgen_futures::spawn_local(async {
+                            let _ = crate::connect().await;
+                        });
+                    }
+                },
+                options.init,
+            );
+        }
+    });
+    player
+}
+
+/// The signals of the closest [`provide_spotify_player`], panics if there is none
+pub fn use_spotify_player() -> SpotifyPlayer {
+    expect_context()
+}
+
+/// The latest state, `None` if there is no playback on this device
+pub fn use_player_state() -> ReadSignal<Option<StateChange>> {
+    use_spotify_player().state
+}
+
+/// The current track, only changes when the track does
+pub fn use_current_track() -> Memo<Option<Track>> {
+    let state = use_player_state();
+    create_memo(move |_| {
+        state.with(|state| {
+            state
+                .as_ref()
+                .map(|state| state.track_window.current_track.clone())
+        })
+    })
+}
+
+/// The position in milliseconds, interpolated between state changes while playing
+pub fn use_position() -> ReadSignal<i32> {
+    use_spotify_player().position
+}
+
+/// The local volume, `None` before the player is initialized
+pub fn use_volume() -> ReadSignal<Option<f32>> {
+    use_spotify_player().volume
+}
+
+/// Where the player is in its life
+pub fn use_lifecycle() -> ReadSignal<Lifecycle> {
+    use_spotify_player().lifecycle
+}
+
+/// Actions that send commands through the [`PlayerStore`](crate::store::PlayerStore), so the signals update right away
+#[derive(Clone, Copy)]
+pub struct PlayerActions {
+    pub pause: Action<(), Result<(), SdkError>>,
+    pub resume: Action<(), Result<(), SdkError>>,
+    pub toggle_play: Action<(), Result<(), SdkError>>,
+    pub next_track: Action<(), Result<(), SdkError>>,
+    pub previous_track: Action<(), Result<(), SdkError>>,
+    /// Seek to the position in milliseconds
+    pub seek: Action<u32, Result<(), SdkError>>,
+    /// Set the volume between 0 and 1
+    pub set_volume: Action<f32, Result<(), SdkError>>,
+    /// See [`activate_element`](crate::activate_element), dispatch it from a click handler
+    pub activate_element: Action<(), Result<(), SdkError>>,
+}
+
+/// Creates the [`PlayerActions`] in the current component
+pub fn use_player_actions() -> PlayerActions {
+    PlayerActions {
+        pause: command_action(|_: &()| Command::Pause),
+        resume: command_action(|_: &()| Command::Resume),
+        toggle_play: command_action(|_: &()| Command::TogglePlay),
+        next_track: command_action(|_: &()| Command::NextTrack),
+        previous_track: command_action(|_: &()| Command::PreviousTrack),
+        seek: command_action(|position_ms: &u32| Command::Seek(*position_ms)),
+        set_volume: command_action(|volume: &f32| Command::SetVolume(*volume)),
+        activate_element: create_action(|_: &()| async { crate::activate_element().await }),
+    }
+}
+
+pub(crate) fn command_action<I, F>(command: F) -> Action<I, Result<(), SdkError>>
+where
+    I: 'static,
+    F: Fn(&I) -> Command + 'static,
+{
+    create_action(move |input: &I| {
+        let command = command(input);
+        async move { player_store().send(command).await }
+    })
+}
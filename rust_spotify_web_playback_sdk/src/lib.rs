@@ -6,7 +6,7 @@
 //! ## [Repo](https://github.com/KOEGlike/rust_spotify_web_playback_sdk)
 //!
 //! # Example in leptos:
-//! ```rust
+//! ```rust,ignore
 //! use leptos::*;
 //! #[component]
 //! fn Player() -> impl IntoView {
@@ -87,18 +87,50 @@
 //! }
 //! ```
 
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
 
+#[cfg(feature = "auth")]
+pub mod auth;
+pub mod backend;
+pub mod command_queue;
+#[cfg(feature = "components")]
+pub mod components;
+#[cfg(feature = "custom_element")]
+pub mod custom_element;
+#[cfg(feature = "dioxus")]
+pub mod dioxus;
+pub mod error;
+pub mod events;
+#[cfg(feature = "js-api")]
+pub mod js_api;
 pub mod js_wrapper;
+#[cfg(feature = "leptos")]
+pub mod leptos;
+pub mod reconnect;
+pub mod restrictions;
+pub mod simulated;
+pub mod store;
 pub mod structs;
+pub mod token;
+mod tracker;
+pub mod watchdog;
+#[cfg(feature = "web_api")]
+pub mod web_api;
+#[cfg(feature = "yew")]
+pub mod yew;
 pub mod prelude {
     pub use crate::{
-        js_wrapper::player_ready,
+        error::{ErrorKind, Recovery, SdkError},
+        events::{add_event_listener, remove_event_listener, Event},
         structs::{
             state_change::StateChange,
             web_playback::{Error, Player, State},
-            Track,
+            RepeatMode, Track,
         },
         *,
     };
@@ -108,6 +140,63 @@ pub mod prelude {
     pub use rust_spotify_web_playback_sdk_macro::*;
 }
 
+/// The scopes the Web Playback SDK needs
+pub const SCOPES: [&str; 4] = [
+    "streaming",
+    "user-read-email",
+    "user-read-private",
+    "user-modify-playback-state",
+];
+
+/// The token closure passed to `init`, shared between the SDK and the Web API client
+pub(crate) type OAuthProvider = Rc<RefCell<dyn FnMut() -> String>>;
+
+thread_local! {
+    static OAUTH: RefCell<Option<OAuthProvider>> = const { RefCell::new(None) };
+}
+
+/// Returns the token closure passed to `init`, if it was called
+#[cfg_attr(not(feature = "web_api"), allow(dead_code))]
+pub(crate) fn oauth_provider() -> Option<OAuthProvider> {
+    OAUTH.with(|oauth| oauth.borrow().clone())
+}
+
+thread_local! {
+    static DEVICE_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The device id the `ready` event reported, `None` before the `ready` event and after a `not_ready` event
+pub fn device_id() -> Option<String> {
+    DEVICE_ID.with(|device_id| device_id.borrow().clone())
+}
+
+/// The options of [`init_with_options`]
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// The name of the player, visible across all Spotify Connect devices.
+    pub name: String,
+    /// The initial volume of the player, between 0 and 1.
+    pub volume: f32,
+    /// Whether to enable media session support.
+    pub enable_media_session: bool,
+    /// Transfer playback to this device with the Web API once the `ready` event arrives, `None` to wait for the user to pick it.
+    #[cfg(feature = "web_api")]
+    pub auto_activate: Option<web_api::AutoActivate>,
+}
+
+impl InitOptions {
+    /// The default options with the given player name
+    pub fn new(name: &str) -> Self {
+        InitOptions {
+            name: name.into(),
+            volume: 1.0,
+            enable_media_session: false,
+            #[cfg(feature = "web_api")]
+            auto_activate: None,
+        }
+    }
+}
+
 ///this function adds the script to the document, and creates an instance of the Spotify.Player class, if you don't call this function all the other functions will be useless
 /// # Arguments
 /// * `oauth` - A closure that returns a String containing the Spotify OAuth token.
@@ -121,45 +210,234 @@ where
     T: FnMut() -> String + 'static,
     F: FnMut() + 'static,
 {
-    let oauth = Closure::wrap(Box::new(oauth) as Box<dyn FnMut() -> String>);
-    let on_ready = Closure::wrap(Box::new(on_ready) as Box<dyn FnMut()>);
-    //leak these closures so they don't get cleaned up
-    let oauth = Box::leak(Box::new(oauth)) as &'static Closure<dyn FnMut() -> String>;
-    let on_ready = Box::leak(Box::new(on_ready)) as &'static Closure<dyn FnMut()>;
-    js_wrapper::init(oauth, on_ready, name.into(), volume, enable_media_session);
+    init_with_options(
+        oauth,
+        on_ready,
+        InitOptions {
+            volume,
+            enable_media_session,
+            ..InitOptions::new(name)
+        },
+    );
+}
+
+/// Same as [`init`], with the options that don't fit in its arguments.
+/// Does nothing outside of the browser, so server side rendering can call it, unless another [`backend`] is installed.
+/// # Arguments
+/// * `oauth` - A closure that returns a String containing the Spotify OAuth token.
+/// * `on_ready` - A closure that is called when the Web Playback SDK is ready.
+/// * `options` - The player options, see [`InitOptions`].
+pub fn init_with_options<T, F>(oauth: T, mut on_ready: F, options: InitOptions)
+where
+    T: FnMut() -> String + 'static,
+    F: FnMut() + 'static,
+{
+    if ensure_browser().is_err() {
+        return;
+    }
+    let oauth: OAuthProvider = Rc::new(RefCell::new(oauth));
+    OAUTH.with(|provider| *provider.borrow_mut() = Some(oauth.clone()));
+    #[cfg(feature = "web_api")]
+    let auto_activate = options.auto_activate.clone();
+    let on_ready = Box::new(move || {
+        listen("ready", |player: structs::web_playback::Player| {
+            DEVICE_ID.with(|device_id| *device_id.borrow_mut() = Some(player.device_id.clone()));
+            store::on_ready(player.device_id);
+        });
+        listen("not_ready", |_: structs::web_playback::Player| {
+            DEVICE_ID.with(|device_id| *device_id.borrow_mut() = None);
+            store::on_lifecycle(store::Lifecycle::NotReady);
+        });
+        for event in [
+            "initialization_error",
+            "authentication_error",
+            "account_error",
+        ] {
+            listen(event, move |error: structs::web_playback::Error| {
+                store::on_lifecycle(store::Lifecycle::Failed(error.kind(event)));
+            });
+        }
+        listen(
+            "player_state_changed",
+            |state: Option<structs::state_change::StateChange>| {
+                store::on_state_changed(&state);
+                tracker::on_state_changed(state);
+            },
+        );
+        // the Web API can't reach a player that only exists natively
+        #[cfg(feature = "web_api")]
+        if let Some(auto_activate) = auto_activate
+            .clone()
+            .filter(|_| ensure_event_loop().is_ok())
+        {
+            listen("ready", move |player: structs::web_playback::Player| {
+                wasm_bindgen_futures::spawn_local(web_api::activate(
+                    player.device_id,
+                    auto_activate.clone(),
+                ));
+            });
+        }
+        on_ready()
+    });
+    store::on_init(options.volume);
+    backend::current().init(Box::new(move || (oauth.borrow_mut())()), on_ready, &options);
+}
+
+/// A listener of the crate's own bookkeeping, see [`listen`]
+type OwnListener = Rc<RefCell<dyn FnMut(backend::SdkEvent)>>;
+
+/// Identifies a listener added with [`listen`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OwnListenerId(u32);
+
+thread_local! {
+    static OWN_LISTENERS: RefCell<Vec<(OwnListenerId, String, OwnListener)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_OWN_ID: Cell<u32> = const { Cell::new(0) };
+    /// The events the installed backend calls [`dispatch`] for
+    static FORWARDED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Adds a listener for the crate's own bookkeeping, like `add_listener!` does for the user's listeners.
+/// They all share one backend listener per event, so [`remove_listener`] can add it back after removing the user's.
+pub(crate) fn listen<T, F>(event: &str, mut callback: F) -> OwnListenerId
+where
+    T: backend::Payload,
+    F: FnMut(T) + 'static,
+{
+    let listener: OwnListener = Rc::new(RefCell::new(move |event| {
+        if let Some(payload) = T::from_event(event) {
+            callback(payload)
+        }
+    }));
+    let id = NEXT_OWN_ID.with(|next| {
+        next.set(next.get() + 1);
+        OwnListenerId(next.get())
+    });
+    OWN_LISTENERS.with(|listeners| {
+        listeners
+            .borrow_mut()
+            .push((id, event.to_string(), listener))
+    });
+    let forwarded = FORWARDED.with(|forwarded| forwarded.borrow().iter().any(|name| name == event));
+    if !forwarded {
+        forward(event);
+    }
+    id
+}
+
+/// Removes a listener added with [`listen`], the backend listener stays and calls the others
+pub(crate) fn unlisten(id: OwnListenerId) {
+    OWN_LISTENERS.with(|listeners| listeners.borrow_mut().retain(|(other, _, _)| *other != id));
+}
+
+/// Adds the backend listener that calls the crate's own listeners of `event`
+fn forward(event: &str) {
+    let name = event.to_string();
+    if backend::current().add_listener(event, Box::new(move |event| dispatch(&name, event))) {
+        FORWARDED.with(|forwarded| forwarded.borrow_mut().push(event.to_string()));
+    }
+}
+
+/// Called by [`backend::set_backend`], the own listeners were added for the old backend, `init` adds them again
+pub(crate) fn forget_own_listeners() {
+    OWN_LISTENERS.with(|listeners| listeners.borrow_mut().clear());
+    FORWARDED.with(|forwarded| forwarded.borrow_mut().clear());
+}
+
+fn dispatch(name: &str, event: backend::SdkEvent) {
+    let listeners: Vec<OwnListener> = OWN_LISTENERS.with(|listeners| {
+        listeners
+            .borrow()
+            .iter()
+            .filter(|(_, other, _)| other == name)
+            .map(|(_, _, listener)| listener.clone())
+            .collect()
+    });
+    for listener in listeners {
+        // a listener that makes the player emit again isn't called recursively
+        if let Ok(mut listener) = listener.try_borrow_mut() {
+            listener(event.clone());
+        }
+    }
+}
+
+/// Milliseconds since the unix epoch
+pub(crate) fn now_ms() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::now();
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or_default();
+}
+
+/// Wait for `ms` milliseconds
+pub(crate) async fn sleep(ms: u32) {
+    #[cfg(target_arch = "wasm32")]
+    let _ = wasm_bindgen_futures::JsFuture::from(js_wrapper::sleep(ms)).await;
+    // a timer thread, so the timeouts work with another backend in native tests
+    #[cfg(not(target_arch = "wasm32"))]
+    futures_timer::Delay::new(std::time::Duration::from_millis(u64::from(ms))).await;
+}
+
+/// Fails with [`SdkError::NotInBrowser`](error::SdkError::NotInBrowser) on native targets, even with another backend.
+/// For the parts that run in the background of the page, they need its event loop and its `window`.
+pub(crate) fn ensure_event_loop() -> Result<(), error::SdkError> {
+    if cfg!(target_arch = "wasm32") {
+        Ok(())
+    } else {
+        Err(error::SdkError::NotInBrowser)
+    }
+}
+
+/// Fails with [`SdkError::NotInBrowser`](error::SdkError::NotInBrowser) where the backend can't run,
+/// like the Web Playback SDK on native targets
+pub(crate) fn ensure_browser() -> Result<(), error::SdkError> {
+    if backend::current().available() {
+        Ok(())
+    } else {
+        Err(error::SdkError::NotInBrowser)
+    }
+}
+
+/// Same as [`ensure_browser`], and fails with [`SdkError::PlayerNotReady`](error::SdkError::PlayerNotReady) before the player exists
+pub(crate) fn ensure_ready() -> Result<(), error::SdkError> {
+    ensure_browser()?;
+    if player_ready() {
+        Ok(())
+    } else {
+        Err(error::SdkError::PlayerNotReady)
+    }
+}
+
+/// Check if the player object is ready, always false where the backend can't run
+pub fn player_ready() -> bool {
+    let backend = backend::current();
+    backend.available() && backend.player_ready()
 }
 
 /// Connect our Web Playback SDK instance to Spotify with the credentials provided during initialization.
 ///
 /// # Response
 /// a Promise containing a Boolean (either true or false) with the success of the connection.
-pub async fn connect() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::connect();
-    let result = match JsFuture::from(promise).await {
-        Ok(e) => e,
-        Err(e) => return Err(format!("{:#?}", e)),
-    };
-    match result.as_bool() {
-        Some(b) => {
-            if b {
-                Ok(())
-            } else {
-                Err("could not connect".into())
-            }
-        }
-        None => Err(format!("not bool, error: {:#?}", result)),
+pub async fn connect() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    if backend::current()
+        .connect()
+        .await
+        .map_err(error::SdkError::Command)?
+    {
+        Ok(())
+    } else {
+        Err(error::SdkError::Command("could not connect".into()))
     }
 }
 
 /// Closes the current session our Web Playback SDK has with Spotify.
-pub fn disconnect() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    js_wrapper::disconnect();
+pub fn disconnect() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    backend::current().disconnect();
     Ok(())
 }
 
@@ -167,6 +445,7 @@ pub fn disconnect() -> Result<(), String> {
 ///
 /// # Response
 /// Returns a Boolean. Returns true if the event name is valid with registered callbacks from #addListener.
+/// Always false with another backend, it has no JS callbacks.
 ///
 /// # Arguments
 /// * `event` - A valid event name. See Web Playback SDK Events.
@@ -174,11 +453,9 @@ pub fn disconnect() -> Result<(), String> {
 pub fn remove_specific_listener(
     event: &str,
     callback: &Closure<dyn FnMut(JsValue)>,
-) -> Result<bool, JsValue> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    Ok(if event_check(event) {
+) -> Result<bool, error::SdkError> {
+    ensure_ready()?;
+    Ok(if event_check(event) && js_wrapper::player_ready() {
         js_wrapper::removeSpecificListener(event.to_string(), callback)
     } else {
         false
@@ -199,7 +476,8 @@ fn event_check(event: &str) -> bool {
     )
 }
 
-/// Remove an event listener in the Web Playback SDK.
+/// Remove the event listeners added with `add_listener!` or [`backend::add_listener`] in the Web Playback SDK.
+/// The crate's own listeners, which keep [`device_id`], the store and the confirmed commands up to date, stay.
 ///
 /// # Response
 /// Returns a Boolean. Returns true if the event name is valid with
@@ -207,18 +485,30 @@ fn event_check(event: &str) -> bool {
 ///
 /// # Arguments
 /// * `event` - A valid event name. See Web Playback SDK Events.
-pub fn remove_listener(event: &str) -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
+pub fn remove_listener(event: &str) -> Result<(), error::SdkError> {
+    ensure_ready()?;
     if event_check(event) {
-        if js_wrapper::removeListener(event.to_string()) {
+        if backend::current().remove_listener(event) {
+            // the SDK removed the forwarding listener too
+            let forwarded = FORWARDED.with(|forwarded| {
+                let mut forwarded = forwarded.borrow_mut();
+                let before = forwarded.len();
+                forwarded.retain(|name| name != event);
+                forwarded.len() != before
+            });
+            if forwarded {
+                forward(event);
+            }
             Ok(())
         } else {
-            Err("the event name is not valid with registered callbacks from add_listener".into())
+            Err(error::SdkError::InvalidRequest(
+                "the event name is not valid with registered callbacks from add_listener".into(),
+            ))
         }
     } else {
-        Err("event does not exist".into())
+        Err(error::SdkError::InvalidRequest(
+            "event does not exist".into(),
+        ))
     }
 }
 
@@ -227,20 +517,12 @@ use crate::structs::web_playback::State;
 ///
 /// # Response
 /// Returns a Promise. It will return either a WebPlaybackState object or null depending on if the user is successfully connected. Wrapped in result if the future throws an exception
-pub async fn get_current_state() -> Result<Option<State>, String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::getCurrentState();
-    let result = match JsFuture::from(promise).await {
-        Ok(e) => e,
-        Err(e) => return Err(format!("{:#?}", e)),
-    };
-    // web_sys::console::log_1(&result);
-    if result.is_null() {
-        return Ok(None);
-    }
-    Ok(Some(structs::from_js(result)))
+pub async fn get_current_state() -> Result<Option<State>, error::SdkError> {
+    ensure_ready()?;
+    backend::current()
+        .get_current_state()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Rename the Spotify Player device. This is visible across all Spotify Connect devices.
@@ -250,34 +532,24 @@ pub async fn get_current_state() -> Result<Option<State>, String> {
 ///
 /// # Arguments
 /// * `name` - The new desired player name.
-pub async fn set_name(name: String) -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::setName(name);
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+pub async fn set_name(name: String) -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    backend::current()
+        .set_name(name)
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Get the local volume currently set in the Web Playback SDK.
 ///
 /// # Response
 /// Returns a Promise containing the local volume (as a Float between 0 and 1).
-pub async fn get_volume() -> Result<f32, String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::getVolume();
-    let result = match JsFuture::from(promise).await {
-        Ok(e) => e,
-        Err(e) => return Err(format!("{:#?}", e)),
-    };
-    match serde_wasm_bindgen::from_value(result) {
-        Ok(e) => Ok(e),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+pub async fn get_volume() -> Result<f32, error::SdkError> {
+    ensure_ready()?;
+    backend::current()
+        .get_volume()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Set the local volume for the Web Playback SDK.
@@ -287,107 +559,115 @@ pub async fn get_volume() -> Result<f32, String> {
 ///
 /// # Arguments
 /// * `volume` - The new desired volume for local playback. Between 0 and 1. Note: On iOS devices, the audio level is always under the user’s physical control. The volume property is not settable in JavaScript. Reading the volume property always returns 1. More details can be found in the iOS-specific Considerations documentation page by Apple.
-pub async fn set_volume(volume: f32) -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::setVolume(volume);
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+pub async fn set_volume(volume: f32) -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    backend::current()
+        .set_volume(volume)
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Pause the local playback.
 ///
 /// # Response
-/// Returns an empty Promise
-pub async fn pause() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::pause();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows pausing.
+pub async fn pause() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    check_allowed(restrictions::Action::Pausing)?;
+    backend::current()
+        .pause()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Resume the local playback.
 ///
 /// # Response
-/// Returns an empty Promise
-pub async fn resume() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::resume();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows resuming.
+pub async fn resume() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    check_allowed(restrictions::Action::Resuming)?;
+    backend::current()
+        .resume()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Resume/pause the local playback.
 ///
 /// # Response
-/// Returns an empty Promise
-pub async fn toggle_play() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::togglePlay();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows resuming or pausing.
+pub async fn toggle_play() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    if let Some(state) = tracker::last_state() {
+        let action = if state.paused {
+            restrictions::Action::Resuming
+        } else {
+            restrictions::Action::Pausing
+        };
+        restrictions::check(&state, action)?;
     }
+    backend::current()
+        .toggle_play()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Seek to a position in the current track in local playback.
 ///
 /// # Response
-/// Returns an empty Promise
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows seeking, like during an ad.
 ///
 /// # Arguments
 /// * `position_ms` - The position in milliseconds to seek to.
-pub async fn seek(position_ms: u32) -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::seek(position_ms);
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+pub async fn seek(position_ms: u32) -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    check_allowed(restrictions::Action::Seeking)?;
+    backend::current()
+        .seek(position_ms)
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Switch to the previous track in local playback.
 ///
 /// # Response
-/// Returns an empty Promise
-pub async fn previous_track() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::previousTrack();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
-    }
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows skipping back.
+pub async fn previous_track() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    check_allowed(restrictions::Action::SkippingPrev)?;
+    backend::current()
+        .previous_track()
+        .await
+        .map_err(error::SdkError::Command)
 }
 
 /// Skip to the next track in local playback.
 ///
 /// # Response
-/// Returns an empty Promise
-pub async fn next_track() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
-    }
-    let promise = js_wrapper::nextTrack();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
+/// Returns an empty Promise, [`SdkError::Disallowed`](error::SdkError::Disallowed) without calling the SDK if the latest state disallows skipping, like during an ad.
+pub async fn next_track() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    check_allowed(restrictions::Action::SkippingNext)?;
+    backend::current()
+        .next_track()
+        .await
+        .map_err(error::SdkError::Command)
+}
+
+/// What the latest state allows, so buttons can be disabled before they are clicked.
+/// Everything is allowed before the first `player_state_changed` event, like the commands assume.
+pub fn capabilities() -> restrictions::Capabilities {
+    tracker::last_state()
+        .map(|state| restrictions::Capabilities::from_state(&state))
+        .unwrap_or_default()
+}
+
+/// Fails with [`SdkError::Disallowed`](error::SdkError::Disallowed) if the latest state doesn't allow `action`
+fn check_allowed(action: restrictions::Action) -> Result<(), error::SdkError> {
+    match tracker::last_state() {
+        Some(state) => restrictions::check(&state, action),
+        None => Ok(()),
     }
 }
 
@@ -398,13 +678,249 @@ pub async fn next_track() -> Result<(), String> {
 ///
 /// # Response
 /// Returns an empty Promise
-pub async fn activate_element() -> Result<(), String> {
-    if !js_wrapper::player_ready() {
-        return Err("player not ready".into());
+pub async fn activate_element() -> Result<(), error::SdkError> {
+    ensure_ready()?;
+    backend::current()
+        .activate_element()
+        .await
+        .map_err(error::SdkError::Command)
+}
+
+/// How long the commands that go through the Web API wait for the state change that confirms them,
+/// and a sensible `timeout_ms` for the `*_confirmed` commands
+pub const STATE_TIMEOUT_MS: u32 = 10_000;
+
+/// How far the position of the confirming state can be from the target of [`seek_confirmed`]
+pub const SEEK_TOLERANCE_MS: u32 = 1500;
+
+/// Same as [`pause`], but waits for the state change that shows the playback paused.
+///
+/// # Response
+/// Resolves with the confirming state change, right away if the playback is already paused,
+/// or [`SdkError::Timeout`](error::SdkError::Timeout) if it doesn't arrive in `timeout_ms`.
+///
+/// # Arguments
+/// * `timeout_ms` - How long to wait for the state change, see [`STATE_TIMEOUT_MS`].
+pub async fn pause_confirmed(
+    timeout_ms: u32,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    if let Some(state) = tracker::last_state().filter(|state| state.paused) {
+        return Ok(state);
+    }
+    confirm(pause(), |state| state.paused, timeout_ms).await
+}
+
+/// Same as [`resume`], but waits for the state change that shows the playback playing.
+///
+/// # Response
+/// Resolves with the confirming state change, right away if the playback is already playing,
+/// or [`SdkError::Timeout`](error::SdkError::Timeout) if it doesn't arrive in `timeout_ms`.
+///
+/// # Arguments
+/// * `timeout_ms` - How long to wait for the state change, see [`STATE_TIMEOUT_MS`].
+pub async fn resume_confirmed(
+    timeout_ms: u32,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    if let Some(state) = tracker::last_state().filter(|state| !state.paused) {
+        return Ok(state);
     }
-    let promise = js_wrapper::activateElement();
-    match JsFuture::from(promise).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{:#?}", e)),
+    confirm(resume(), |state| !state.paused, timeout_ms).await
+}
+
+/// Same as [`seek`], but waits for the state change with a position within [`SEEK_TOLERANCE_MS`] of `position_ms`.
+///
+/// # Response
+/// Resolves with the confirming state change,
+/// or [`SdkError::Timeout`](error::SdkError::Timeout) if it doesn't arrive in `timeout_ms`.
+///
+/// # Arguments
+/// * `position_ms` - The position in milliseconds to seek to.
+/// * `timeout_ms` - How long to wait for the state change, see [`STATE_TIMEOUT_MS`].
+pub async fn seek_confirmed(
+    position_ms: u32,
+    timeout_ms: u32,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    confirm(
+        seek(position_ms),
+        move |state| {
+            (i64::from(state.position) - i64::from(position_ms)).unsigned_abs()
+                <= u64::from(SEEK_TOLERANCE_MS)
+        },
+        timeout_ms,
+    )
+    .await
+}
+
+/// Same as [`next_track`], but waits for the state change with another current track.
+///
+/// # Response
+/// Resolves with the confirming state change,
+/// [`SdkError::NoPlayback`](error::SdkError::NoPlayback) if there is no playback on this device,
+/// or [`SdkError::Timeout`](error::SdkError::Timeout) if the state change doesn't arrive in `timeout_ms`.
+///
+/// # Arguments
+/// * `timeout_ms` - How long to wait for the state change, see [`STATE_TIMEOUT_MS`].
+pub async fn next_track_confirmed(
+    timeout_ms: u32,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    let state = tracker::last_state().ok_or(error::SdkError::NoPlayback)?;
+    let uri = state.track_window.current_track.uri;
+    confirm(
+        next_track(),
+        move |state| state.track_window.current_track.uri != uri,
+        timeout_ms,
+    )
+    .await
+}
+
+/// Sends an SDK command and waits for the first state change that matches `predicate`
+async fn confirm<P>(
+    command: impl std::future::Future<Output = Result<(), error::SdkError>>,
+    predicate: P,
+    timeout_ms: u32,
+) -> Result<structs::state_change::StateChange, error::SdkError>
+where
+    P: Fn(&structs::state_change::StateChange) -> bool + 'static,
+{
+    ensure_ready()?;
+    let confirmation = tracker::expect(predicate);
+    command.await?;
+    confirmation.wait(timeout_ms).await
+}
+
+/// Start playing a context or a list of tracks on this device, through the Web API. Requires the `web_api` feature.
+///
+/// # Response
+/// Resolves with the state change that shows the requested context and track,
+/// or [`SdkError::Timeout`] if it doesn't arrive in [`STATE_TIMEOUT_MS`].
+/// The state doesn't say where a track is in its context, so with [`Offset::Position`](web_api::Offset::Position)
+/// only the context is confirmed, and `position_ms` is never confirmed.
+///
+/// # Errors
+/// [`SdkError::InvalidRequest`] without sending anything if `uris` is empty.
+///
+/// # Arguments
+/// * `request` - What to play, see [`web_api::PlayRequest`].
+#[cfg(feature = "web_api")]
+pub async fn play(
+    request: web_api::PlayRequest,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    use web_api::{Offset, PlayRequest};
+
+    ensure_browser()?;
+    let expected = match &request {
+        PlayRequest::Tracks { uris, .. } => Expected {
+            context: None,
+            track: Some(uris.first().cloned().ok_or_else(|| {
+                error::SdkError::InvalidRequest("there are no tracks to play".into())
+            })?),
+        },
+        PlayRequest::Context { uri, offset, .. } => Expected {
+            context: Some(uri.clone()),
+            track: match offset {
+                Some(Offset::Uri(track)) => Some(track.clone()),
+                Some(Offset::Position(_)) | None => None,
+            },
+        },
+    };
+    let device_id = device_id().ok_or(error::SdkError::PlayerNotReady)?;
+    let api = web_api::WebApi::from_init()?;
+    let confirmation = tracker::expect(move |state| expected.matches(state));
+    api.start_playback(Some(&device_id), &request.into())
+        .await?;
+    confirmation.wait(STATE_TIMEOUT_MS).await
+}
+
+/// Turn shuffle on or off for this device, through the Web API. Requires the `web_api` feature.
+///
+/// # Response
+/// Resolves with the state change that shows the new shuffle state,
+/// [`SdkError::Disallowed`] if the current state doesn't allow toggling shuffle.
+///
+/// # Arguments
+/// * `shuffle` - true: shuffle the playback, false: do not shuffle the playback.
+#[cfg(feature = "web_api")]
+pub async fn set_shuffle(
+    shuffle: bool,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    ensure_browser()?;
+    let device_id = device_id().ok_or(error::SdkError::PlayerNotReady)?;
+    let api = web_api::WebApi::from_init()?;
+    if let Some(state) = tracker::last_state() {
+        restrictions::check(&state, restrictions::Action::TogglingShuffle)?;
+        if state.shuffle == shuffle {
+            return Ok(state);
+        }
+    }
+    let confirmation = tracker::expect(move |state| state.shuffle == shuffle);
+    api.set_shuffle(shuffle, Some(&device_id)).await?;
+    confirmation.wait(STATE_TIMEOUT_MS).await
+}
+
+/// Set the repeat mode of this device, through the Web API. Requires the `web_api` feature.
+///
+/// # Response
+/// Resolves with the state change that shows the new repeat mode,
+/// [`SdkError::Disallowed`] if the current state doesn't allow toggling that repeat mode.
+///
+/// # Arguments
+/// * `mode` - The new repeat mode.
+#[cfg(feature = "web_api")]
+pub async fn set_repeat(
+    mode: structs::RepeatMode,
+) -> Result<structs::state_change::StateChange, error::SdkError> {
+    ensure_browser()?;
+    let device_id = device_id().ok_or(error::SdkError::PlayerNotReady)?;
+    let api = web_api::WebApi::from_init()?;
+    if let Some(state) = tracker::last_state() {
+        let current = structs::RepeatMode::from_sdk(state.repeat_mode);
+        if current == mode {
+            return Ok(state);
+        }
+        restrictions::check(&state, restrictions::repeat_action(current, mode))?;
+    }
+    let confirmation =
+        tracker::expect(move |state| structs::RepeatMode::from_sdk(state.repeat_mode) == mode);
+    api.set_repeat(mode, Some(&device_id)).await?;
+    confirmation.wait(STATE_TIMEOUT_MS).await
+}
+
+/// Switch to the next repeat mode like the Spotify apps do (off, context, track), skipping the one the current state disallows.
+/// Requires the `web_api` feature.
+///
+/// # Response
+/// Resolves with the state change that shows the new repeat mode,
+/// [`SdkError::NoPlayback`] if there is no playback on this device.
+#[cfg(feature = "web_api")]
+pub async fn cycle_repeat() -> Result<structs::state_change::StateChange, error::SdkError> {
+    ensure_browser()?;
+    let state = tracker::last_state().ok_or(error::SdkError::NoPlayback)?;
+    let current = structs::RepeatMode::from_sdk(state.repeat_mode);
+    let next = [current.next(), current.next().next()]
+        .into_iter()
+        .find(|mode| {
+            restrictions::check(&state, restrictions::repeat_action(current, *mode)).is_ok()
+        })
+        .unwrap_or(current.next());
+    set_repeat(next).await
+}
+
+/// What the state change that confirms [`play`] shows
+#[cfg(feature = "web_api")]
+struct Expected {
+    context: Option<String>,
+    track: Option<String>,
+}
+
+#[cfg(feature = "web_api")]
+impl Expected {
+    fn matches(&self, state: &structs::state_change::StateChange) -> bool {
+        self.context
+            .as_ref()
+            .is_none_or(|uri| state.context.uri.as_ref() == Some(uri))
+            && self
+                .track
+                .as_ref()
+                .is_none_or(|uri| structs::is_track(&state.track_window.current_track, uri))
     }
 }
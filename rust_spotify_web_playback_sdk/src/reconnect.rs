@@ -0,0 +1,255 @@
+//! An opt-in supervisor that calls [`connect`](crate::connect) again when the player drops off,
+//! because the network flapped, the token expired or the tab was in the background.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{prelude as sp, reconnect};
+//!
+//! sp::init(oauth, || {
+//!     let supervisor = reconnect::start(reconnect::ReconnectOptions::default()).unwrap();
+//! }, "example player", 1.0, false);
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use wasm_bindgen::{prelude::Closure, JsCast};
+
+use crate::{
+    error::SdkError,
+    events::{emit, Event},
+    store::Lifecycle,
+    structs::web_playback::{Error, Player},
+    token::TokenCache,
+    OwnListenerId,
+};
+
+/// Why the supervisor is reconnecting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReconnectReason {
+    /// The SDK emitted `not_ready`
+    NotReady,
+    /// The SDK emitted `authentication_error`, the token is refreshed before connecting
+    AuthenticationError,
+    /// The browser came back online after the player dropped off
+    Online,
+    /// The page became visible after the player dropped off
+    Visible,
+}
+
+/// The options of [`start`]
+#[derive(Clone)]
+pub struct ReconnectOptions {
+    /// Give up after this many failed `connect` calls in a row.
+    pub max_attempts: u32,
+    /// The delay before the first attempt, it doubles with every attempt.
+    pub initial_delay_ms: u32,
+    /// The delay never grows over this.
+    pub max_delay_ms: u32,
+    /// Refreshed before retrying after an `authentication_error`, `None` to let the SDK call the `oauth` closure again.
+    pub token_cache: Option<TokenCache>,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        ReconnectOptions {
+            max_attempts: 8,
+            initial_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            token_cache: None,
+        }
+    }
+}
+
+/// The running supervisor, it keeps running when this is dropped, call [`stop`](Supervisor::stop) to stop it
+#[derive(Clone)]
+pub struct Supervisor {
+    inner: Rc<Inner>,
+}
+
+type WindowListener = (web_sys::EventTarget, &'static str, Closure<dyn FnMut()>);
+
+struct Inner {
+    options: ReconnectOptions,
+    stopped: Cell<bool>,
+    running: Cell<bool>,
+    /// The player was ready at least once, before that the first `connect` is still running
+    was_ready: Cell<bool>,
+    sdk_listeners: RefCell<Vec<OwnListenerId>>,
+    window_listeners: RefCell<Vec<WindowListener>>,
+}
+
+/// Starts watching the player, call it once the player exists, for example in the `on_ready` closure of `init`.
+///
+/// # Errors
+/// [`SdkError::PlayerNotReady`] if the player doesn't exist yet,
+/// [`SdkError::NotInBrowser`] on native targets, with the [`SimulatedPlayer`](crate::simulated::SimulatedPlayer) too.
+pub fn start(options: ReconnectOptions) -> Result<Supervisor, SdkError> {
+    crate::ensure_ready()?;
+    crate::ensure_event_loop()?;
+    let supervisor = Supervisor {
+        inner: Rc::new(Inner {
+            options,
+            stopped: Cell::new(false),
+            running: Cell::new(false),
+            was_ready: Cell::new(
+                crate::device_id().is_some()
+                    || crate::store::player_store().get().lifecycle == Lifecycle::NotReady,
+            ),
+            sdk_listeners: RefCell::new(Vec::new()),
+            window_listeners: RefCell::new(Vec::new()),
+        }),
+    };
+
+    let weak = Rc::downgrade(&supervisor.inner);
+    let ready = crate::listen("ready", move |_: Player| {
+        if let Some(inner) = weak.upgrade() {
+            inner.was_ready.set(true);
+        }
+    });
+    let weak = Rc::downgrade(&supervisor.inner);
+    let not_ready = crate::listen("not_ready", move |_: Player| {
+        trigger(&weak, ReconnectReason::NotReady)
+    });
+    let weak = Rc::downgrade(&supervisor.inner);
+    let authentication_error = crate::listen("authentication_error", move |_: Error| {
+        trigger(&weak, ReconnectReason::AuthenticationError)
+    });
+    supervisor
+        .inner
+        .sdk_listeners
+        .replace(vec![ready, not_ready, authentication_error]);
+
+    if let Some(window) = web_sys::window() {
+        let document = window.document();
+        supervisor.add_window_listener(window.unchecked_into(), "online", ReconnectReason::Online);
+        if let Some(document) = document {
+            supervisor.add_window_listener(
+                document.unchecked_into(),
+                "visibilitychange",
+                ReconnectReason::Visible,
+            );
+        }
+    }
+    Ok(supervisor)
+}
+
+impl Supervisor {
+    /// Stops reconnecting and removes the supervisor's listeners, an attempt that is already running finishes
+    pub fn stop(&self) {
+        self.inner.stopped.set(true);
+        for id in self.inner.sdk_listeners.borrow_mut().drain(..) {
+            crate::unlisten(id);
+        }
+        for (target, event, closure) in self.inner.window_listeners.borrow_mut().drain(..) {
+            let _ =
+                target.remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+        }
+    }
+
+    /// True while the supervisor is waiting for or running an attempt
+    pub fn is_reconnecting(&self) -> bool {
+        self.inner.running.get()
+    }
+
+    fn add_window_listener(
+        &self,
+        target: web_sys::EventTarget,
+        event: &'static str,
+        reason: ReconnectReason,
+    ) {
+        let weak = Rc::downgrade(&self.inner);
+        let closure = Closure::<dyn FnMut()>::new(move || {
+            // these only matter if the player dropped off while the page was offline or hidden,
+            // not while the first `connect` is running
+            let dropped_off = weak
+                .upgrade()
+                .is_some_and(|inner| inner.was_ready.get() && crate::device_id().is_none());
+            if dropped_off && is_online() && is_visible() {
+                trigger(&weak, reason)
+            }
+        });
+        if target
+            .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .is_ok()
+        {
+            self.inner
+                .window_listeners
+                .borrow_mut()
+                .push((target, event, closure));
+        }
+    }
+}
+
+fn trigger(inner: &std::rc::Weak<Inner>, reason: ReconnectReason) {
+    let Some(inner) = inner.upgrade() else {
+        return;
+    };
+    if inner.stopped.get() || inner.running.get() {
+        return;
+    }
+    inner.running.set(true);
+    wasm_bindgen_futures::spawn_local(async move {
+        run(&inner, reason).await;
+        inner.running.set(false);
+    });
+}
+
+async fn run(inner: &Inner, reason: ReconnectReason) {
+    let options = &inner.options;
+    for attempt in 1..=options.max_attempts {
+        let delay_ms = backoff(options, attempt);
+        emit(Event::ReconnectAttempt {
+            attempt,
+            delay_ms,
+            reason,
+        });
+        crate::sleep(delay_ms).await;
+        if inner.stopped.get() {
+            return;
+        }
+        // the online and visibilitychange listeners trigger a new run once connecting makes sense
+        if !is_online() || !is_visible() {
+            return;
+        }
+        if reason == ReconnectReason::AuthenticationError {
+            if let Some(token_cache) = &options.token_cache {
+                if token_cache.refresh().await.is_err() {
+                    continue;
+                }
+            }
+        }
+        if crate::connect().await.is_ok() {
+            emit(Event::Reconnected { attempts: attempt });
+            return;
+        }
+    }
+    emit(Event::ReconnectGaveUp {
+        attempts: options.max_attempts,
+    });
+}
+
+/// The exponential delay of `attempt` with up to 50% random jitter, so many tabs don't reconnect at the same time.
+/// The jitter never pushes it over `max_delay_ms`.
+fn backoff(options: &ReconnectOptions, attempt: u32) -> u32 {
+    let delay = options
+        .initial_delay_ms
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(options.max_delay_ms);
+    let jitter = (f64::from(delay) * 0.5 * js_sys::Math::random()) as u32;
+    delay.saturating_add(jitter).min(options.max_delay_ms)
+}
+
+fn is_online() -> bool {
+    web_sys::window()
+        .map(|window| window.navigator().on_line())
+        .unwrap_or(true)
+}
+
+fn is_visible() -> bool {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .map(|document| document.visibility_state() == web_sys::VisibilityState::Visible)
+        .unwrap_or(true)
+}
@@ -0,0 +1,142 @@
+//! Checks the `disallows` and `restrictions` of the latest state before a command is sent
+use crate::{
+    error::SdkError,
+    structs::{state_change::StateChange, RepeatMode},
+};
+
+/// A player action Spotify can disallow, like seeking during an ad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Pausing,
+    Resuming,
+    Seeking,
+    SkippingNext,
+    SkippingPrev,
+    TogglingShuffle,
+    TogglingRepeatContext,
+    TogglingRepeatTrack,
+}
+
+impl Action {
+    /// Every action, in the order of the fields of `Disallows`
+    pub const ALL: [Action; 8] = [
+        Action::Pausing,
+        Action::Resuming,
+        Action::Seeking,
+        Action::SkippingNext,
+        Action::SkippingPrev,
+        Action::TogglingShuffle,
+        Action::TogglingRepeatContext,
+        Action::TogglingRepeatTrack,
+    ];
+}
+
+/// What the player allows right now, to disable buttons before the user clicks them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Capabilities {
+    disallowed: Vec<(Action, Vec<String>)>,
+}
+
+impl Capabilities {
+    /// The capabilities of `state`, [`capabilities`](crate::capabilities) uses the latest state
+    pub fn from_state(state: &StateChange) -> Self {
+        Capabilities {
+            disallowed: Action::ALL
+                .into_iter()
+                .filter_map(|action| disallowed(state, action).map(|reasons| (action, reasons)))
+                .collect(),
+        }
+    }
+
+    /// True if the action is allowed
+    pub fn allows(&self, action: Action) -> bool {
+        self.reasons(action).is_none()
+    }
+
+    /// The `disallow_*_reasons` of a disallowed action, can be empty. `None` if the action is allowed.
+    pub fn reasons(&self, action: Action) -> Option<&[String]> {
+        self.disallowed
+            .iter()
+            .find(|(disallowed, _)| *disallowed == action)
+            .map(|(_, reasons)| reasons.as_slice())
+    }
+
+    pub fn can_pause(&self) -> bool {
+        self.allows(Action::Pausing)
+    }
+
+    pub fn can_resume(&self) -> bool {
+        self.allows(Action::Resuming)
+    }
+
+    pub fn can_seek(&self) -> bool {
+        self.allows(Action::Seeking)
+    }
+
+    pub fn can_skip_next(&self) -> bool {
+        self.allows(Action::SkippingNext)
+    }
+
+    pub fn can_skip_prev(&self) -> bool {
+        self.allows(Action::SkippingPrev)
+    }
+
+    pub fn can_toggle_shuffle(&self) -> bool {
+        self.allows(Action::TogglingShuffle)
+    }
+
+    /// True if the player can switch to `mode` from the repeat mode of the state
+    pub fn can_set_repeat(&self, from: RepeatMode, to: RepeatMode) -> bool {
+        from == to || self.allows(repeat_action(from, to))
+    }
+}
+
+/// The action needed to go from the `from` repeat mode to `to`
+pub(crate) fn repeat_action(from: RepeatMode, to: RepeatMode) -> Action {
+    match (from, to) {
+        (_, RepeatMode::Context) | (RepeatMode::Context, RepeatMode::Off) => {
+            Action::TogglingRepeatContext
+        }
+        _ => Action::TogglingRepeatTrack,
+    }
+}
+
+/// Returns [`SdkError::Disallowed`] if `state` doesn't allow `action`
+pub(crate) fn check(state: &StateChange, action: Action) -> Result<(), SdkError> {
+    match disallowed(state, action) {
+        Some(reasons) => Err(SdkError::Disallowed { action, reasons }),
+        None => Ok(()),
+    }
+}
+
+/// The reasons `state` disallows `action`, `None` if it's allowed
+fn disallowed(state: &StateChange, action: Action) -> Option<Vec<String>> {
+    let disallows = &state.disallows;
+    let restrictions = &state.restrictions;
+    let (disallowed, reasons) = match action {
+        Action::Pausing => (disallows.pausing, &restrictions.disallow_pausing_reasons),
+        Action::Resuming => (disallows.resuming, &restrictions.disallow_resuming_reasons),
+        Action::Seeking => (disallows.seeking, &restrictions.disallow_seeking_reasons),
+        Action::SkippingNext => (
+            disallows.skipping_next,
+            &restrictions.disallow_skipping_next_reasons,
+        ),
+        Action::SkippingPrev => (
+            disallows.skipping_prev,
+            &restrictions.disallow_skipping_prev_reasons,
+        ),
+        Action::TogglingShuffle => (
+            disallows.toggling_shuffle,
+            &restrictions.disallow_toggling_shuffle_reasons,
+        ),
+        Action::TogglingRepeatContext => (
+            disallows.toggling_repeat_context,
+            &restrictions.disallow_toggling_repeat_context_reasons,
+        ),
+        Action::TogglingRepeatTrack => (
+            disallows.toggling_repeat_track,
+            &restrictions.disallow_toggling_repeat_track_reasons,
+        ),
+    };
+    (disallowed == Some(true)).then(|| reasons.clone().unwrap_or_default())
+}
@@ -0,0 +1,618 @@
+//! An in-memory player that behaves like the Web Playback SDK, for developing and testing without Spotify.
+//! It works in the browser and in native `cargo test`, install it with [`set_backend`](crate::backend::set_backend) before `init`.
+//!
+//! Like the SDK it emits `ready` when it connects, `player_state_changed` after every command and
+//! `not_ready`, `player_state_changed` with `None` or the error events when asked to.
+//! Connecting transfers the queue to it, paused at the start of the first track.
+//! The position moves with the clock while playing, [`advance`](SimulatedPlayer::advance) moves the clock forward,
+//! and the next track starts when the current one ends. In the browser a timer ends the track on time,
+//! natively the tracks only end when the player is called.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{backend, prelude as sp, simulated::{self, SimulatedPlayer}};
+//!
+//! let player = SimulatedPlayer::new(vec![
+//!     simulated::track("1", "First song", 180_000),
+//!     simulated::track("2", "Second song", 200_000),
+//! ]);
+//! backend::set_backend(player.clone());
+//! sp::init(|| "token".into(), || {}, "test player", 1.0, false);
+//! sp::connect().await?;
+//! sp::resume().await?;
+//! player.advance(181_000);
+//! assert_eq!(player.current_track().unwrap().name, "Second song");
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use futures::future::{ready, FutureExt};
+
+use crate::{
+    backend::{BackendFuture, Listener, PlayerBackend, SdkEvent},
+    structs::{
+        state_change::{self, Features, Restrictions, Speed, StateChange},
+        web_playback::{self, Context, Error, Player, State},
+        Album, Artist, Track,
+    },
+    InitOptions,
+};
+
+/// The device id the simulated player reports in the `ready` event
+pub const DEVICE_ID: &str = "simulated-device";
+
+/// How far into a track `previous_track` restarts it instead of going back, like the Spotify apps
+const RESTART_AFTER_MS: u32 = 3000;
+
+/// How many tracks the track window shows before and after the current one, like the SDK
+const WINDOW: usize = 2;
+
+/// A track with the fields the SDK fills for a regular song
+///
+/// # Arguments
+/// * `id` - The Spotify id, the uri is `spotify:track:{id}`.
+/// * `name` - The name of the track.
+/// * `duration_ms` - How long the track is.
+pub fn track(id: &str, name: &str, duration_ms: u32) -> Track {
+    Track {
+        album: Album {
+            uri: format!("spotify:album:{id}"),
+            name: format!("{name} (album)"),
+            images: vec![],
+        },
+        artists: vec![Artist {
+            uri: format!("spotify:artist:{id}"),
+            url: None,
+            name: "Simulated artist".into(),
+        }],
+        duration_ms,
+        id: id.into(),
+        is_playable: true,
+        linked_from: None,
+        media_type: "audio".into(),
+        metadata: None,
+        name: name.into(),
+        track_type: "audio".into(),
+        uid: format!("uid-{id}"),
+        uri: format!("spotify:track:{id}"),
+    }
+}
+
+/// The simulated player, cloning it is cheap and the clones share the player
+#[derive(Clone)]
+pub struct SimulatedPlayer {
+    inner: Rc<Inner>,
+}
+
+type Shared = Rc<RefCell<Listener>>;
+
+struct Inner {
+    tracks: Vec<Track>,
+    index: Cell<usize>,
+    paused: Cell<bool>,
+    /// The position when the clock read `since_ms`
+    position_ms: Cell<u32>,
+    since_ms: Cell<f64>,
+    /// What [`SimulatedPlayer::advance`] added to the clock
+    skew_ms: Cell<f64>,
+    volume: Cell<f32>,
+    name: RefCell<String>,
+    oauth: RefCell<Option<Box<dyn FnMut() -> String>>>,
+    created: Cell<bool>,
+    connected: Cell<bool>,
+    /// Playback is on this device, false before connecting and after a transfer away
+    active: Cell<bool>,
+    playback_id: Cell<u32>,
+    /// Bumped every time the end of the track is timed again, so only the latest timer ends it
+    timer: Cell<u32>,
+    listeners: RefCell<Vec<(&'static str, Shared)>>,
+}
+
+impl SimulatedPlayer {
+    /// A player with `tracks` in its queue, in the order they play
+    pub fn new(tracks: Vec<Track>) -> Self {
+        SimulatedPlayer {
+            inner: Rc::new(Inner {
+                tracks,
+                index: Cell::new(0),
+                paused: Cell::new(true),
+                position_ms: Cell::new(0),
+                since_ms: Cell::new(0.0),
+                skew_ms: Cell::new(0.0),
+                volume: Cell::new(1.0),
+                name: RefCell::new(String::new()),
+                oauth: RefCell::new(None),
+                created: Cell::new(false),
+                connected: Cell::new(false),
+                active: Cell::new(false),
+                playback_id: Cell::new(0),
+                timer: Cell::new(0),
+                listeners: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Moves the clock forward by `ms`, starting the next tracks if the current one ends while playing
+    pub fn advance(&self, ms: u32) {
+        self.inner
+            .skew_ms
+            .set(self.inner.skew_ms.get() + f64::from(ms));
+        self.sync();
+        self.time_end();
+    }
+
+    /// Emits an error event, like `"account_error"` or `"playback_error"`.
+    ///
+    /// # Response
+    /// Returns false if `event` isn't an error event, then nothing is emitted.
+    pub fn emit_error(&self, event: &str, message: &str) -> bool {
+        let error = Error {
+            message: message.into(),
+        };
+        match SdkEvent::error(event, error) {
+            Some(event) => {
+                self.emit(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The device goes offline like when the network drops, it emits `not_ready` and has to connect again
+    pub fn go_offline(&self) {
+        self.inner.connected.set(false);
+        self.emit(SdkEvent::NotReady(player()));
+    }
+
+    /// Playback moves to another device, it emits `player_state_changed` with `None`
+    pub fn transfer_away(&self) {
+        self.pause_clock();
+        self.inner.active.set(false);
+        self.emit(SdkEvent::PlayerStateChanged(None));
+    }
+
+    /// The track that is playing, `None` if playback isn't on this device
+    pub fn current_track(&self) -> Option<Track> {
+        self.sync();
+        self.active_track().cloned()
+    }
+
+    /// The position in the current track in milliseconds
+    pub fn position(&self) -> u32 {
+        self.sync();
+        self.position_now()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.get()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.inner.connected.get()
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.inner.volume.get()
+    }
+
+    /// The name set by `init` or `set_name`
+    pub fn name(&self) -> String {
+        self.inner.name.borrow().clone()
+    }
+
+    /// The payload the next `player_state_changed` event would have, `None` if playback isn't on this device
+    pub fn state_change(&self) -> Option<StateChange> {
+        self.sync();
+        let track = self.active_track()?;
+        let paused = self.inner.paused.get();
+        let no_next = self.inner.index.get() + 1 >= self.inner.tracks.len();
+        let (next_tracks, previous_tracks) = self.window();
+        let reasons = |disallowed: bool, reason: &str| disallowed.then(|| vec![reason.to_string()]);
+        Some(StateChange {
+            context: context(),
+            disallows: state_change::Disallows {
+                pausing: Some(paused),
+                peeking_next: Some(no_next),
+                peeking_prev: Some(false),
+                resuming: Some(!paused),
+                seeking: Some(false),
+                skipping_next: Some(no_next),
+                skipping_prev: Some(false),
+                toggling_repeat_context: Some(false),
+                toggling_repeat_track: Some(false),
+                toggling_shuffle: Some(false),
+                undefined: None,
+            },
+            duration: track.duration_ms as i32,
+            loading: false,
+            paused,
+            playback_features: Features {
+                hifi_status: "NONE".into(),
+                playback_speed: Speed {
+                    current: 1.0,
+                    restricted: false,
+                    selected: 1.0,
+                },
+                signal_ids: None,
+            },
+            playback_id: format!("{:032x}", self.inner.playback_id.get()),
+            playback_quality: "VERY_HIGH".into(),
+            playback_speed: 1.0,
+            position: self.position_now() as i32,
+            repeat_mode: 0,
+            restrictions: Restrictions {
+                disallow_pausing_reasons: reasons(paused, "already_paused"),
+                disallow_peeking_next_reasons: reasons(no_next, "no_next_track"),
+                disallow_peeking_prev_reasons: None,
+                disallow_resuming_reasons: reasons(!paused, "not_paused"),
+                disallow_seeking_reasons: None,
+                disallow_skipping_next_reasons: reasons(no_next, "no_next_track"),
+                disallow_skipping_prev_reasons: None,
+                disallow_toggling_repeat_context_reasons: None,
+                disallow_toggling_repeat_track_reasons: None,
+                disallow_toggling_shuffle_reasons: None,
+                undefined: None,
+            },
+            shuffle: false,
+            shuffle_mode: Some(0),
+            timestamp: self.now() as i64,
+            track_window: state_change::TrackWindow {
+                current_track: track.clone(),
+                next_tracks,
+                previous_tracks,
+            },
+        })
+    }
+
+    /// The payload `get_current_state` resolves with, `None` if playback isn't on this device
+    pub fn state(&self) -> Option<State> {
+        let state = self.state_change()?;
+        Some(State {
+            context: state.context,
+            disallows: web_playback::Disallows {
+                pausing: state.disallows.pausing,
+                peeking_next: state.disallows.peeking_next,
+                peeking_prev: state.disallows.peeking_prev,
+                resuming: state.disallows.resuming,
+                seeking: state.disallows.seeking,
+                skipping_next: state.disallows.skipping_next,
+                skipping_prev: state.disallows.skipping_prev,
+            },
+            loading: state.loading,
+            paused: state.paused,
+            position: state.position,
+            repeat_mode: state.repeat_mode,
+            shuffle: state.shuffle,
+            track_window: web_playback::TrackWindow {
+                current_track: state.track_window.current_track,
+                previous_tracks: state.track_window.previous_tracks,
+                next_tracks: state.track_window.next_tracks,
+            },
+        })
+    }
+
+    fn now(&self) -> f64 {
+        crate::now_ms() + self.inner.skew_ms.get()
+    }
+
+    fn active_track(&self) -> Option<&Track> {
+        if !self.inner.active.get() {
+            return None;
+        }
+        self.inner.tracks.get(self.inner.index.get())
+    }
+
+    /// The tracks after and before the current one, the closest first
+    fn window(&self) -> (Vec<Track>, Vec<Track>) {
+        let index = self.inner.index.get();
+        let next = self.inner.tracks.iter().skip(index + 1).take(WINDOW);
+        let previous = self.inner.tracks[..index].iter().rev().take(WINDOW).rev();
+        (next.cloned().collect(), previous.cloned().collect())
+    }
+
+    /// The position without looking at the end of the track
+    fn position_now(&self) -> u32 {
+        let position = self.inner.position_ms.get();
+        if self.inner.paused.get() {
+            return position;
+        }
+        let elapsed = (self.now() - self.inner.since_ms.get()).max(0.0);
+        let duration = self.active_track().map_or(0, |track| track.duration_ms);
+        (f64::from(position) + elapsed).min(f64::from(duration)) as u32
+    }
+
+    fn set_position(&self, position_ms: u32) {
+        self.inner.position_ms.set(position_ms);
+        self.inner.since_ms.set(self.now());
+    }
+
+    fn pause_clock(&self) {
+        self.set_position(self.position_now());
+        self.inner.paused.set(true);
+    }
+
+    /// Starts the tracks the clock went past, emitting a state change for each one like the SDK does
+    fn sync(&self) {
+        while !self.inner.paused.get() {
+            let Some(duration) = self.active_track().map(|track| track.duration_ms) else {
+                return;
+            };
+            let ended_at = self.inner.since_ms.get() + f64::from(duration)
+                - f64::from(self.inner.position_ms.get());
+            if self.now() < ended_at {
+                return;
+            }
+            if self.inner.index.get() + 1 < self.inner.tracks.len() {
+                self.inner.index.set(self.inner.index.get() + 1);
+                self.inner.position_ms.set(0);
+                self.inner.since_ms.set(ended_at);
+            } else {
+                // the end of the queue, it stops at the end of the last track
+                self.inner.position_ms.set(duration);
+                self.inner.paused.set(true);
+            }
+            self.changed();
+        }
+    }
+
+    /// Emits the `player_state_changed` event with the current state
+    fn changed(&self) {
+        self.emit(SdkEvent::PlayerStateChanged(
+            self.state_change().map(Box::new),
+        ));
+        self.time_end();
+    }
+
+    /// In the browser, ends the current track when the clock gets to its end, without waiting for a call
+    fn time_end(&self) {
+        let timer = self.inner.timer.get().wrapping_add(1);
+        self.inner.timer.set(timer);
+        // there is no background outside of the browser
+        if crate::ensure_event_loop().is_err() || self.inner.paused.get() {
+            return;
+        }
+        let Some(duration) = self.active_track().map(|track| track.duration_ms) else {
+            return;
+        };
+        let ended_at = self.inner.since_ms.get() + f64::from(duration)
+            - f64::from(self.inner.position_ms.get());
+        let delay = (ended_at - self.now())
+            .clamp(0.0, f64::from(u32::MAX))
+            .ceil() as u32;
+        let inner = Rc::downgrade(&self.inner);
+        wasm_bindgen_futures::spawn_local(async move {
+            crate::sleep(delay).await;
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            if inner.timer.get() != timer {
+                return;
+            }
+            let player = SimulatedPlayer { inner };
+            player.sync();
+            // the timer fired a bit early, or the track didn't end and nothing timed it again
+            if player.inner.timer.get() == timer {
+                player.time_end();
+            }
+        });
+    }
+
+    fn emit(&self, event: SdkEvent) {
+        let name = event.name();
+        let listeners: Vec<Shared> = self
+            .inner
+            .listeners
+            .borrow()
+            .iter()
+            .filter(|(event, _)| *event == name)
+            .map(|(_, listener)| listener.clone())
+            .collect();
+        for listener in listeners {
+            // a listener that makes the player emit again isn't called recursively
+            if let Ok(mut listener) = listener.try_borrow_mut() {
+                listener(event.clone());
+            }
+        }
+    }
+
+    /// Runs a command that needs playback on this device, and emits the state change that shows it
+    fn command<F>(&self, command: F) -> BackendFuture<()>
+    where
+        F: FnOnce(&Self) -> Result<(), String>,
+    {
+        self.sync();
+        let result = if !self.inner.connected.get() {
+            Err("the player is not connected".into())
+        } else if self.active_track().is_none() {
+            Err("no playback on this device".into())
+        } else {
+            command(self).map(|_| self.changed())
+        };
+        ready(result).boxed_local()
+    }
+
+    fn play(&self, index: usize) {
+        self.inner.index.set(index);
+        self.inner.playback_id.set(self.inner.playback_id.get() + 1);
+        self.set_position(0);
+    }
+}
+
+fn player() -> Player {
+    Player {
+        device_id: DEVICE_ID.into(),
+    }
+}
+
+fn context() -> Context {
+    Context {
+        uri: Some("spotify:playlist:simulated".into()),
+        metadata: None,
+    }
+}
+
+/// The events a listener can be added for, with the `'static` name the simulated player keeps
+fn event_name(event: &str) -> Option<&'static str> {
+    [
+        "ready",
+        "not_ready",
+        "player_state_changed",
+        "autoplay_failed",
+        "initialization_error",
+        "authentication_error",
+        "account_error",
+        "playback_error",
+    ]
+    .into_iter()
+    .find(|name| *name == event)
+}
+
+impl PlayerBackend for SimulatedPlayer {
+    fn init(
+        &self,
+        oauth: Box<dyn FnMut() -> String>,
+        mut on_ready: Box<dyn FnMut()>,
+        options: &InitOptions,
+    ) {
+        *self.inner.name.borrow_mut() = options.name.clone();
+        self.inner.volume.set(options.volume);
+        *self.inner.oauth.borrow_mut() = Some(oauth);
+        self.inner.created.set(true);
+        // there is no script to load
+        on_ready();
+    }
+
+    fn player_ready(&self) -> bool {
+        self.inner.created.get()
+    }
+
+    fn connect(&self) -> BackendFuture<bool> {
+        if self.inner.connected.get() {
+            return ready(Ok(true)).boxed_local();
+        }
+        let token = self.inner.oauth.borrow_mut().as_mut().map(|oauth| oauth());
+        if token.unwrap_or_default().is_empty() {
+            self.emit_error("authentication_error", "Authentication failed");
+            return ready(Ok(false)).boxed_local();
+        }
+        self.inner.connected.set(true);
+        self.emit(SdkEvent::Ready(player()));
+        if !self.inner.active.get() && !self.inner.tracks.is_empty() {
+            self.inner.active.set(true);
+            self.inner.paused.set(true);
+            self.play(0);
+            self.changed();
+        }
+        ready(Ok(true)).boxed_local()
+    }
+
+    fn disconnect(&self) {
+        if self.inner.connected.replace(false) && self.inner.active.get() {
+            self.transfer_away();
+        }
+    }
+
+    fn add_listener(&self, event: &str, callback: Listener) -> bool {
+        let Some(event) = event_name(event) else {
+            return false;
+        };
+        self.inner
+            .listeners
+            .borrow_mut()
+            .push((event, Rc::new(RefCell::new(callback))));
+        true
+    }
+
+    fn remove_listener(&self, event: &str) -> bool {
+        let Some(event) = event_name(event) else {
+            return false;
+        };
+        self.inner
+            .listeners
+            .borrow_mut()
+            .retain(|(name, _)| *name != event);
+        true
+    }
+
+    fn get_current_state(&self) -> BackendFuture<Option<State>> {
+        ready(Ok(self.state())).boxed_local()
+    }
+
+    fn set_name(&self, name: String) -> BackendFuture<()> {
+        *self.inner.name.borrow_mut() = name;
+        ready(Ok(())).boxed_local()
+    }
+
+    fn get_volume(&self) -> BackendFuture<f32> {
+        ready(Ok(self.inner.volume.get())).boxed_local()
+    }
+
+    fn set_volume(&self, volume: f32) -> BackendFuture<()> {
+        if !(0.0..=1.0).contains(&volume) {
+            return ready(Err(format!("the volume {volume} is not between 0 and 1"))).boxed_local();
+        }
+        self.inner.volume.set(volume);
+        ready(Ok(())).boxed_local()
+    }
+
+    fn pause(&self) -> BackendFuture<()> {
+        self.command(|player| {
+            player.pause_clock();
+            Ok(())
+        })
+    }
+
+    fn resume(&self) -> BackendFuture<()> {
+        self.command(|player| {
+            player.set_position(player.position_now());
+            player.inner.paused.set(false);
+            Ok(())
+        })
+    }
+
+    fn toggle_play(&self) -> BackendFuture<()> {
+        self.sync();
+        if self.inner.paused.get() {
+            self.resume()
+        } else {
+            self.pause()
+        }
+    }
+
+    fn seek(&self, position_ms: u32) -> BackendFuture<()> {
+        self.command(|player| {
+            let duration = player.active_track().map_or(0, |track| track.duration_ms);
+            player.set_position(position_ms.min(duration));
+            Ok(())
+        })
+    }
+
+    fn previous_track(&self) -> BackendFuture<()> {
+        self.command(|player| {
+            let index = player.inner.index.get();
+            if player.position_now() > RESTART_AFTER_MS || index == 0 {
+                player.set_position(0);
+            } else {
+                player.play(index - 1);
+            }
+            Ok(())
+        })
+    }
+
+    fn next_track(&self) -> BackendFuture<()> {
+        self.command(|player| {
+            let index = player.inner.index.get();
+            if index + 1 >= player.inner.tracks.len() {
+                return Err("no next track".into());
+            }
+            player.play(index + 1);
+            Ok(())
+        })
+    }
+
+    fn activate_element(&self) -> BackendFuture<()> {
+        ready(Ok(())).boxed_local()
+    }
+}
@@ -0,0 +1,554 @@
+//! A framework agnostic store of everything the player reports: the latest state, the volume, the lifecycle and the device id.
+//! It's fed by the player's events, and commands sent through it update the state optimistically,
+//! a command that fails, or that the first state after it returned doesn't confirm, is rolled back.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{
+//!     command_queue::Command,
+//!     store::{player_store, Snapshot},
+//! };
+//!
+//! let store = player_store();
+//! store.subscribe(|snapshot| {
+//!     // render snapshot.state and snapshot.volume
+//! });
+//! // only called when the paused flag changes, not on every position update
+//! store.select(Snapshot::paused, |paused| {
+//!     // update the play button
+//! });
+//! wasm_bindgen_futures::spawn_local(async move {
+//!     // the subscribers see the paused state right away
+//!     let _ = store.send(Command::Pause).await;
+//! });
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+
+use crate::{
+    command_queue::Command,
+    error::ErrorKind,
+    error::SdkError,
+    events::{emit, Event},
+    structs::{state_change::StateChange, Track},
+};
+
+/// Where the player is in its life
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Lifecycle {
+    /// `init` wasn't called yet
+    #[default]
+    Uninitialized,
+    /// `init` was called, the SDK didn't emit `ready` yet
+    Initializing,
+    /// The SDK emitted `ready`, the device id is known
+    Ready,
+    /// The SDK emitted `not_ready`, the device went offline
+    NotReady,
+    /// The SDK emitted an `initialization_error`, `authentication_error` or `account_error`
+    Failed(ErrorKind),
+}
+
+/// What the store publishes to its subscribers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// The latest state with the optimistic changes applied, `None` if there is no playback on this device
+    pub state: Option<StateChange>,
+    /// The local volume, `None` before `init`
+    pub volume: Option<f32>,
+    pub lifecycle: Lifecycle,
+    /// The device id the `ready` event reported
+    pub device_id: Option<String>,
+}
+
+impl Snapshot {
+    /// Selects the current track, `None` if there is no playback on this device
+    pub fn current_track(&self) -> Option<Track> {
+        self.state
+            .as_ref()
+            .map(|state| state.track_window.current_track.clone())
+    }
+
+    /// Selects the paused flag, `None` if there is no playback on this device
+    pub fn paused(&self) -> Option<bool> {
+        self.state.as_ref().map(|state| state.paused)
+    }
+
+    /// Selects the position in milliseconds, `None` if there is no playback on this device
+    pub fn position(&self) -> Option<i32> {
+        self.state.as_ref().map(|state| state.position)
+    }
+
+    /// Selects the volume
+    pub fn volume(&self) -> Option<f32> {
+        self.volume
+    }
+
+    /// Selects the lifecycle
+    pub fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle
+    }
+}
+
+/// Identifies a subscriber added with [`PlayerStore::subscribe`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u32);
+
+type Subscriber = Rc<RefCell<dyn FnMut(&Snapshot)>>;
+
+/// The store of the player, get it with [`player_store`], cloning it is cheap and the clones share the state
+#[derive(Clone)]
+pub struct PlayerStore {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    /// The latest state the SDK reported
+    confirmed: RefCell<Option<StateChange>>,
+    /// The volume of the latest `set_volume` the SDK accepted, with the id it was sent with
+    confirmed_volume: Cell<(u32, Option<f32>)>,
+    snapshot: RefCell<Snapshot>,
+    pending: RefCell<Vec<Optimistic>>,
+    /// The volumes sent to the SDK that it didn't accept or reject yet
+    pending_volumes: RefCell<Vec<(u32, f32)>>,
+    subscribers: RefCell<Vec<(SubscriptionId, Subscriber)>>,
+    next_id: Cell<u32>,
+}
+
+/// A change that was published before the SDK confirmed it
+struct Optimistic {
+    id: u32,
+    apply: Apply,
+    confirmed_by: ConfirmedBy,
+    /// The command returned, so the next state judges the change
+    returned: bool,
+    /// The latest state that arrived while the command ran shows the change
+    confirmed_early: bool,
+    sender: oneshot::Sender<Result<(), SdkError>>,
+}
+
+thread_local! {
+    static STORE: PlayerStore = PlayerStore {
+        inner: Rc::new(Inner {
+            confirmed: RefCell::new(None),
+            confirmed_volume: Cell::new((0, None)),
+            snapshot: RefCell::new(Snapshot::default()),
+            pending: RefCell::new(Vec::new()),
+            pending_volumes: RefCell::new(Vec::new()),
+            subscribers: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        }),
+    };
+}
+
+/// The store fed by the player's events
+pub fn player_store() -> PlayerStore {
+    STORE.with(Clone::clone)
+}
+
+/// Called with every `player_state_changed` payload
+pub(crate) fn on_state_changed(state: &Option<StateChange>) {
+    player_store().reconcile(state.clone());
+}
+
+/// Called by `init` with the initial volume
+pub(crate) fn on_init(volume: f32) {
+    let store = player_store();
+    store
+        .inner
+        .confirmed_volume
+        .set((store.next_id(), Some(volume)));
+    {
+        let mut snapshot = store.inner.snapshot.borrow_mut();
+        snapshot.volume = Some(volume);
+        snapshot.lifecycle = Lifecycle::Initializing;
+    }
+    store.publish();
+}
+
+/// Called with the device id of the `ready` event
+pub(crate) fn on_ready(device_id: String) {
+    let store = player_store();
+    {
+        let mut snapshot = store.inner.snapshot.borrow_mut();
+        snapshot.lifecycle = Lifecycle::Ready;
+        snapshot.device_id = Some(device_id);
+    }
+    store.publish();
+}
+
+/// Called with the other lifecycle events, the device id is forgotten after `not_ready`
+pub(crate) fn on_lifecycle(lifecycle: Lifecycle) {
+    let store = player_store();
+    {
+        let mut snapshot = store.inner.snapshot.borrow_mut();
+        if lifecycle == Lifecycle::NotReady {
+            snapshot.device_id = None;
+        }
+        snapshot.lifecycle = lifecycle;
+    }
+    store.publish();
+}
+
+impl PlayerStore {
+    /// The latest snapshot, with the optimistic changes applied
+    pub fn get(&self) -> Snapshot {
+        self.inner.snapshot.borrow().clone()
+    }
+
+    /// The latest state with the optimistic changes applied
+    pub fn state(&self) -> Option<StateChange> {
+        self.inner.snapshot.borrow().state.clone()
+    }
+
+    /// True while a command sent through the store waits for its confirmation
+    pub fn is_pending(&self) -> bool {
+        !self.inner.pending.borrow().is_empty()
+    }
+
+    /// Add a subscriber, it's called with every new snapshot, optimistic or confirmed.
+    ///
+    /// # Response
+    /// Returns the id of the subscriber, pass it to [`unsubscribe`](PlayerStore::unsubscribe) to remove it.
+    pub fn subscribe<F>(&self, callback: F) -> SubscriptionId
+    where
+        F: FnMut(&Snapshot) + 'static,
+    {
+        let id = SubscriptionId(self.next_id());
+        self.inner
+            .subscribers
+            .borrow_mut()
+            .push((id, Rc::new(RefCell::new(callback))));
+        id
+    }
+
+    /// Add a subscriber that's only called when the slice `selector` picks out of the snapshot changes,
+    /// for example [`Snapshot::paused`] doesn't fire when only the position changed.
+    ///
+    /// # Response
+    /// Returns the id of the subscriber, pass it to [`unsubscribe`](PlayerStore::unsubscribe) to remove it.
+    ///
+    /// # Arguments
+    /// * `selector` - Picks the slice out of the snapshot.
+    /// * `callback` - Called with the new slice when it's different from the last one.
+    pub fn select<T, S, F>(&self, selector: S, mut callback: F) -> SubscriptionId
+    where
+        T: PartialEq + 'static,
+        S: Fn(&Snapshot) -> T + 'static,
+        F: FnMut(&T) + 'static,
+    {
+        let mut last = selector(&self.get());
+        self.subscribe(move |snapshot| {
+            let selected = selector(snapshot);
+            if selected != last {
+                callback(&selected);
+                last = selected;
+            }
+        })
+    }
+
+    /// Remove a subscriber added with [`subscribe`](PlayerStore::subscribe).
+    ///
+    /// # Response
+    /// Returns true if the subscriber was registered.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscribers = self.inner.subscribers.borrow_mut();
+        let len = subscribers.len();
+        subscribers.retain(|(subscriber_id, _)| *subscriber_id != id);
+        subscribers.len() != len
+    }
+
+    /// Applies the command to the snapshot right away, then sends it to the SDK.
+    /// `Pause`, `Resume`, `TogglePlay`, `Seek`, `SetVolume` and `NextTrack` are applied optimistically,
+    /// `PreviousTrack` isn't because the SDK may restart the current track instead.
+    ///
+    /// # Response
+    /// Resolves once the SDK confirmed the command with a state change.
+    /// If the command fails, the first state after it returned doesn't show the change ([`SdkError::Unconfirmed`]),
+    /// or no state arrives in [`STATE_TIMEOUT_MS`](crate::STATE_TIMEOUT_MS),
+    /// the change is rolled back and [`Event::OptimisticRollback`] is emitted.
+    /// [`SdkError::NotInBrowser`] on native targets, without touching the snapshot.
+    ///
+    /// # Arguments
+    /// * `command` - The command to send.
+    pub async fn send(&self, command: Command) -> Result<(), SdkError> {
+        crate::ensure_browser()?;
+        if let Command::SetVolume(volume) = command {
+            return self.set_volume(volume).await;
+        }
+        let Some((apply, confirmed_by)) =
+            self.state().and_then(|state| optimistic(command, &state))
+        else {
+            return command.run().await;
+        };
+        let id = self.next_id();
+        let (sender, receiver) = oneshot::channel();
+        self.inner.pending.borrow_mut().push(Optimistic {
+            id,
+            apply,
+            confirmed_by,
+            returned: false,
+            confirmed_early: false,
+            sender,
+        });
+        self.recompute();
+
+        let result = match command.run().await {
+            Ok(()) => {
+                self.returned(id);
+                let timeout = Box::pin(crate::sleep(crate::STATE_TIMEOUT_MS));
+                match select(receiver, timeout).await {
+                    Either::Left((Ok(result), _)) => result,
+                    _ => Err(SdkError::Timeout),
+                }
+            }
+            Err(error) => Err(error),
+        };
+        if let Err(error) = &result {
+            // the state that contradicted it already took it out
+            let removed = {
+                let mut pending = self.inner.pending.borrow_mut();
+                let len = pending.len();
+                pending.retain(|optimistic| optimistic.id != id);
+                pending.len() != len
+            };
+            if removed {
+                self.recompute();
+            }
+            emit(Event::OptimisticRollback {
+                command,
+                error: error.clone(),
+            });
+        }
+        result
+    }
+
+    /// Marks the change of a command that returned, it's confirmed right away
+    /// if the latest state that arrived while the command ran shows it
+    fn returned(&self, id: u32) {
+        let confirmed = {
+            let mut pending = self.inner.pending.borrow_mut();
+            let Some(index) = pending.iter().position(|optimistic| optimistic.id == id) else {
+                return;
+            };
+            if !pending[index].confirmed_early {
+                pending[index].returned = true;
+                return;
+            }
+            pending.remove(index)
+        };
+        let _ = confirmed.sender.send(Ok(()));
+        self.recompute();
+    }
+
+    /// The volume has no state change to confirm it, so it's rolled back only if the SDK rejects it.
+    /// The snapshot shows the latest volume sent, so a rejected volume falls back to one that was sent after it.
+    async fn set_volume(&self, volume: f32) -> Result<(), SdkError> {
+        let id = self.next_id();
+        self.inner.pending_volumes.borrow_mut().push((id, volume));
+        self.recompute_volume();
+        let result = crate::set_volume(volume).await;
+        self.inner
+            .pending_volumes
+            .borrow_mut()
+            .retain(|(other, _)| *other != id);
+        match &result {
+            Ok(()) => {
+                if id > self.inner.confirmed_volume.get().0 {
+                    self.inner.confirmed_volume.set((id, Some(volume)));
+                }
+            }
+            Err(error) => {
+                self.recompute_volume();
+                emit(Event::OptimisticRollback {
+                    command: Command::SetVolume(volume),
+                    error: error.clone(),
+                });
+            }
+        }
+        result
+    }
+
+    /// Publishes the latest volume that was sent and wasn't rejected, if it changed
+    fn recompute_volume(&self) {
+        let (confirmed_id, confirmed) = self.inner.confirmed_volume.get();
+        let volume = match self.inner.pending_volumes.borrow().last() {
+            Some((id, volume)) if *id > confirmed_id => Some(*volume),
+            _ => confirmed,
+        };
+        if self.inner.snapshot.borrow().volume == volume {
+            return;
+        }
+        self.inner.snapshot.borrow_mut().volume = volume;
+        self.publish();
+    }
+
+    /// Takes the state the SDK reported as the truth, and judges the optimistic changes of the commands that returned:
+    /// the ones it confirms are dropped, the ones it doesn't are rolled back right away,
+    /// their `send` fails with [`SdkError::Unconfirmed`].
+    /// The changes of the commands that are still running stay, the SDK may report intermediate states before they land.
+    fn reconcile(&self, state: Option<StateChange>) {
+        if let Some(state) = &state {
+            let settled: Vec<Optimistic> = {
+                let mut pending = self.inner.pending.borrow_mut();
+                let (settled, running) = std::mem::take(&mut *pending)
+                    .into_iter()
+                    .partition(|optimistic| optimistic.returned);
+                *pending = running;
+                for optimistic in pending.iter_mut() {
+                    optimistic.confirmed_early = (optimistic.confirmed_by)(state);
+                }
+                settled
+            };
+            for optimistic in settled {
+                let result = if (optimistic.confirmed_by)(state) {
+                    Ok(())
+                } else {
+                    Err(SdkError::Unconfirmed)
+                };
+                let _ = optimistic.sender.send(result);
+            }
+        }
+        *self.inner.confirmed.borrow_mut() = state;
+        self.recompute();
+    }
+
+    /// Applies the pending changes on top of the confirmed state, and publishes the result
+    fn recompute(&self) {
+        let state = self.inner.confirmed.borrow().clone().map(|mut state| {
+            for optimistic in self.inner.pending.borrow().iter() {
+                (optimistic.apply)(&mut state);
+            }
+            state
+        });
+        self.inner.snapshot.borrow_mut().state = state;
+        self.publish();
+    }
+
+    /// Calls every subscriber, subscribers can subscribe and unsubscribe while they are called
+    fn publish(&self) {
+        let snapshot = self.get();
+        let subscribers: Vec<Subscriber> = self
+            .inner
+            .subscribers
+            .borrow()
+            .iter()
+            .map(|(_, subscriber)| subscriber.clone())
+            .collect();
+        for subscriber in subscribers {
+            // a subscriber that sends a command itself is not called recursively
+            if let Ok(mut subscriber) = subscriber.try_borrow_mut() {
+                subscriber(&snapshot);
+            }
+        }
+    }
+
+    fn next_id(&self) -> u32 {
+        let id = self.inner.next_id.get() + 1;
+        self.inner.next_id.set(id);
+        id
+    }
+}
+
+/// How often the framework bindings move the interpolated position forward while playing
+#[cfg_attr(
+    not(any(feature = "leptos", feature = "yew", feature = "dioxus")),
+    allow(dead_code)
+)]
+pub(crate) const POSITION_INTERVAL_MS: u32 = 250;
+
+/// The position of the latest state and when it was received, to interpolate the position between state changes
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(
+    not(any(feature = "leptos", feature = "yew", feature = "dioxus")),
+    allow(dead_code)
+)]
+pub(crate) struct Anchor {
+    position: i32,
+    duration: i32,
+    received_at: f64,
+    pub(crate) playing: bool,
+}
+
+#[cfg_attr(
+    not(any(feature = "leptos", feature = "yew", feature = "dioxus")),
+    allow(dead_code)
+)]
+impl Anchor {
+    pub(crate) fn from_state(state: &Option<StateChange>) -> Self {
+        state
+            .as_ref()
+            .map(|state| Anchor {
+                position: state.position,
+                duration: state.duration,
+                received_at: crate::now_ms(),
+                playing: !state.paused && !state.loading,
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn position(&self) -> i32 {
+        if !self.playing {
+            return self.position;
+        }
+        let elapsed = (crate::now_ms() - self.received_at) as i32;
+        self.position.saturating_add(elapsed).min(self.duration)
+    }
+}
+
+type Apply = Box<dyn Fn(&mut StateChange)>;
+type ConfirmedBy = Box<dyn Fn(&StateChange) -> bool>;
+
+/// How `command` changes the state, and how to recognize the state that confirms it.
+/// `None` if the command can't be predicted from `state`.
+fn optimistic(command: Command, state: &StateChange) -> Option<(Apply, ConfirmedBy)> {
+    match command {
+        Command::Pause => Some((
+            Box::new(|state| state.paused = true),
+            Box::new(|state| state.paused),
+        )),
+        Command::Resume => Some((
+            Box::new(|state| state.paused = false),
+            Box::new(|state| !state.paused),
+        )),
+        Command::TogglePlay => {
+            let paused = !state.paused;
+            Some((
+                Box::new(move |state| state.paused = paused),
+                Box::new(move |state| state.paused == paused),
+            ))
+        }
+        Command::Seek(position_ms) => Some((
+            Box::new(move |state| state.position = position_ms as i32),
+            Box::new(move |state| {
+                (i64::from(state.position) - i64::from(position_ms)).unsigned_abs()
+                    <= u64::from(crate::SEEK_TOLERANCE_MS)
+            }),
+        )),
+        Command::NextTrack if !state.track_window.next_tracks.is_empty() => {
+            let from = state.track_window.current_track.uri.clone();
+            let confirmed_from = from.clone();
+            Some((
+                Box::new(move |state| {
+                    let window = &mut state.track_window;
+                    // applied again on top of every new state, so only skip from the track the command skipped
+                    if window.current_track.uri == from && !window.next_tracks.is_empty() {
+                        let next = window.next_tracks.remove(0);
+                        let current = std::mem::replace(&mut window.current_track, next);
+                        window.previous_tracks.push(current);
+                        state.position = 0;
+                    }
+                }),
+                Box::new(move |state| state.track_window.current_track.uri != confirmed_from),
+            ))
+        }
+        _ => None,
+    }
+}
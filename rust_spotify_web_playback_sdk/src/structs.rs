@@ -1,4 +1,3 @@
-use core::panic;
 use nestify::*;
 use serde::{Deserialize, Serialize};
 
@@ -20,17 +19,80 @@ where
     }
 }
 
+/// True if `track` is the track with `uri`, also when Spotify relinked it to another version of the track
+pub fn is_track(track: &Track, uri: &str) -> bool {
+    track.uri == uri
+        || track
+            .linked_from
+            .as_ref()
+            .and_then(|linked_from| linked_from.uri.as_deref())
+            == Some(uri)
+}
+
+/// The repeat mode of the player, the SDK reports it as a number in `repeat_mode`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    Off,
+    Context,
+    Track,
+}
+
+impl RepeatMode {
+    /// Converts the `repeat_mode` number of `State` and `StateChange`, unknown values are treated as `Off`
+    pub fn from_sdk(repeat_mode: i8) -> Self {
+        match repeat_mode {
+            1 => RepeatMode::Context,
+            2 => RepeatMode::Track,
+            _ => RepeatMode::Off,
+        }
+    }
+
+    /// The mode after this one when cycling through them like the Spotify apps do: off, context, track
+    pub fn next(&self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::Context,
+            RepeatMode::Context => RepeatMode::Track,
+            RepeatMode::Track => RepeatMode::Off,
+        }
+    }
+
+    /// The value the Web API expects in the `state` query parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::Context => "context",
+            RepeatMode::Track => "track",
+        }
+    }
+}
+
+impl Album {
+    /// The smallest image at least `size` pixels wide, or the widest one if they are all smaller.
+    /// Images without a width are only picked if no image has one.
+    pub fn image(&self, size: i32) -> Option<&Image> {
+        let with_width = || self.images.iter().filter(|image| image.width.is_some());
+        with_width()
+            .filter(|image| image.width >= Some(size))
+            .min_by_key(|image| image.width)
+            .or_else(|| with_width().max_by_key(|image| image.width))
+            .or_else(|| self.images.first())
+    }
+}
+
 nest! {
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "js-api", derive(tsify_next::Tsify))]*
+    #[cfg_attr(feature = "js-api", tsify(into_wasm_abi))]
     pub struct Track {
         pub album:
-        #[derive(Serialize, Deserialize, Debug)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
         pub struct Album {
             /// Spotify Album URI
             pub uri: String,
             pub name: String,
             pub images: Vec<
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct Image{
                     pub url: String,
                     pub size: Option<String>,
@@ -40,7 +102,7 @@ nest! {
             >
         },
         pub artists: Vec<
-            #[derive(Serialize, Deserialize, Debug)]
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
             pub struct Artist {
                 pub uri: String,
                 pub url: Option<String>,
@@ -51,7 +113,7 @@ nest! {
         pub id: String,
         pub is_playable: bool,
         pub linked_from: Option<
-            #[derive(Serialize, Deserialize, Debug)]
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
             pub struct LinkedFrom {
                 pub uri: Option<String>,
                 pub id: Option<String>,
@@ -59,7 +121,7 @@ nest! {
         >,
         pub media_type: String,
         pub metadata: Option<
-            #[derive(Serialize, Deserialize, Debug)]
+            #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
             pub struct Metadata {
             }
         >,
@@ -72,34 +134,43 @@ nest! {
 
 pub mod web_playback {
     use super::*;
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub struct Player {
         pub device_id: String,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub struct Error {
         pub message: String,
     }
 
+    impl Error {
+        /// Classifies the message, `event` is the event the error came from, like `"account_error"`
+        pub fn kind(&self, event: &str) -> crate::error::ErrorKind {
+            crate::error::ErrorKind::classify(event, &self.message)
+        }
+    }
+
     nest! {
-        #[derive(Serialize, Deserialize, Debug)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        #[cfg_attr(feature = "js-api", derive(tsify_next::Tsify))]*
+        #[cfg_attr(feature = "js-api", tsify(into_wasm_abi))]
         pub struct State {
             pub context:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct Context {
                     /// The URI of the context
                     pub uri: Option<String>,
                     /// Additional metadata for the context (can be null)
                     pub metadata: Option<
-                        #[derive(Serialize, Deserialize, Debug)]
+                        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                         pub struct ContextMetadata {
                             pub context_description:Option<String>
                         }
                     >
                 },
             pub disallows:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct Disallows {
                     /**A simplified set of restriction controls for
                     The current track. By default, these fields
@@ -118,6 +189,9 @@ pub mod web_playback {
                     pub skipping_next: Option<bool>,
                     pub skipping_prev: Option<bool>,
                 },
+            /// Whether the current track is loading, false if the SDK left it out.
+            #[serde(default)]
+            pub loading: bool,
             /// Whether the current track is paused.
             pub paused: bool,
             /// The position_ms of the current track.
@@ -127,7 +201,7 @@ pub mod web_playback {
             /// True if shuffled, false otherwise.
             pub shuffle: bool,
             pub track_window:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct TrackWindow{
                     /// The track currently on local playback
                     pub current_track: Track,
@@ -144,17 +218,23 @@ pub mod state_change {
     use super::*;
     use crate::structs::web_playback::Context;
 
-
     nest! {
-        #[derive(Serialize, Deserialize, Debug)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+        #[cfg_attr(feature = "js-api", derive(tsify_next::Tsify))]*
+        #[cfg_attr(feature = "js-api", tsify(into_wasm_abi))]
         ///couldn't find any documentation for this js object, in the official docs it says that the event listener returns a WebPlaybackPlayer object, but in practice it returns this object
         pub struct StateChange {
             pub context: Context,
+            // `State` has a `Disallows` and a `TrackWindow` too, they need different names in TypeScript
+            #[cfg_attr(feature = "js-api", tsify(type = "StateChangeDisallows"))]
             pub disallows:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+                #[cfg_attr(feature = "js-api", tsify(type_prefix = "StateChange"))]
                 pub struct Disallows {
+                    pub pausing: Option<bool>,
                     pub peeking_next: Option<bool>,
                     pub peeking_prev: Option<bool>,
+                    pub resuming: Option<bool>,
                     pub seeking: Option<bool>,
                     pub skipping_next: Option<bool>,
                     pub skipping_prev: Option<bool>,
@@ -167,28 +247,31 @@ pub mod state_change {
             pub loading: bool,
             pub paused:bool,
             pub playback_features:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct Features {
                     pub hifi_status: String,
                     pub playback_speed:
-                        #[derive(Serialize, Deserialize, Debug)]
+                        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                         pub struct Speed {
-                            pub current: i32,
+                            /// Podcasts can play at fractional speeds, like 1.5
+                            pub current: f32,
                             pub restricted:bool,
-                            pub selected: i32,
+                            pub selected: f32,
                         },
                     pub signal_ids: Option<Vec<String>>,
                 },
             pub playback_id:String,
             pub playback_quality: String,
-            pub playback_speed:i32,
+            pub playback_speed: f32,
             pub position: i32,
             pub repeat_mode: i8,
             pub restrictions:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
                 pub struct Restrictions {
+                    pub disallow_pausing_reasons: Option<Vec<String>>,
                     pub disallow_peeking_next_reasons: Option<Vec<String>>,
                     pub disallow_peeking_prev_reasons: Option<Vec<String>>,
+                    pub disallow_resuming_reasons: Option<Vec<String>>,
                     pub disallow_seeking_reasons: Option<Vec<String>>,
                     pub disallow_skipping_next_reasons: Option<Vec<String>>,
                     pub disallow_skipping_prev_reasons: Option<Vec<String>>,
@@ -200,11 +283,17 @@ pub mod state_change {
             pub shuffle: bool,
             pub shuffle_mode: Option<i8>,
             pub timestamp: i64,
+            #[cfg_attr(feature = "js-api", tsify(type = "StateChangeTrackWindow"))]
             pub track_window:
-                #[derive(Serialize, Deserialize, Debug)]
+                #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+                #[cfg_attr(feature = "js-api", tsify(type_prefix = "StateChange"))]
                 pub struct TrackWindow {
+                    // the prefix would rename `Track` too
+                    #[cfg_attr(feature = "js-api", tsify(type = "Track"))]
                     pub current_track: Track,
+                    #[cfg_attr(feature = "js-api", tsify(type = "Track[]"))]
                     pub next_tracks: Vec<Track>,
+                    #[cfg_attr(feature = "js-api", tsify(type = "Track[]"))]
                     pub previous_tracks: Vec<Track>,
                 },
         }
@@ -0,0 +1,250 @@
+//! A token cache that knows when the token expires, and refreshes it before the SDK finds out through an `authentication_error`.
+//!
+//! # Example
+//! ```rust,ignore
+//! use rust_spotify_web_playback_sdk::{
+//!     prelude as sp,
+//!     token::{AccessToken, TokenCache},
+//! };
+//!
+//! let cache = TokenCache::new(|| async {
+//!     // ask your backend for a new token
+//!     Ok(AccessToken { access_token: "...".into(), expires_in: 3600 })
+//! });
+//! // the SDK asks for the token when connecting, and the provider can't wait for the first one
+//! cache.refresh().await?;
+//! sp::init(cache.provider(), || {}, "example player", 1.0, false);
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+};
+
+use futures::future::{FutureExt, LocalBoxFuture, Shared};
+use serde::Deserialize;
+
+use crate::{
+    error::SdkError,
+    events::{emit, Event},
+};
+
+/// Refresh the token when it expires in less than this, by default
+pub const DEFAULT_REFRESH_AHEAD_MS: u32 = 60_000;
+
+/// The shortest time between storing a token and refreshing it in the background
+pub const MIN_REFRESH_DELAY_MS: u32 = 5_000;
+
+/// What a refresh returns, the same shape as the Spotify token endpoint response
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AccessToken {
+    pub access_token: String,
+    /// The seconds the token is valid for
+    pub expires_in: u32,
+}
+
+type Refresh = Box<dyn Fn() -> LocalBoxFuture<'static, Result<AccessToken, SdkError>>>;
+type PendingRefresh = Shared<LocalBoxFuture<'static, Result<String, SdkError>>>;
+
+/// Caches the access token with its expiry, cloning it is cheap and the clones share the token
+#[derive(Clone)]
+pub struct TokenCache {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    refresh: Refresh,
+    token: RefCell<Option<(String, u64)>>,
+    refresh_ahead_ms: Cell<u32>,
+    pending: RefCell<Option<PendingRefresh>>,
+    /// Bumped every time a token is stored, so only the timer of the latest token refreshes
+    generation: Cell<u32>,
+}
+
+impl TokenCache {
+    /// Creates an empty cache, [`refresh`](TokenCache::refresh) fetches the first token
+    ///
+    /// # Arguments
+    /// * `refresh` - Fetches a new token, called at most once at a time.
+    pub fn new<F, Fut>(refresh: F) -> Self
+    where
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<AccessToken, SdkError>> + 'static,
+    {
+        TokenCache {
+            inner: Rc::new(Inner {
+                refresh: Box::new(move || refresh().boxed_local()),
+                token: RefCell::new(None),
+                refresh_ahead_ms: Cell::new(DEFAULT_REFRESH_AHEAD_MS),
+                pending: RefCell::new(None),
+                generation: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Creates a cache that gets its tokens with a GET request to `url`, for example an endpoint of your backend.
+    /// The response has to be JSON with `access_token` and `expires_in`. Requires the `web_api` feature.
+    #[cfg(feature = "web_api")]
+    pub fn from_endpoint(url: &str) -> Self {
+        let url = url.to_string();
+        let http = reqwest::Client::new();
+        Self::new(move || {
+            let request = http.get(&url);
+            async move {
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| SdkError::Request(e.to_string()))?;
+                let status = response.status().as_u16();
+                let body = response
+                    .text()
+                    .await
+                    .map_err(|e| SdkError::Request(e.to_string()))?;
+                if !(200..300).contains(&status) {
+                    return Err(SdkError::Api {
+                        status,
+                        message: body,
+                        reason: None,
+                    });
+                }
+                serde_json::from_str(&body).map_err(|e| SdkError::Deserialize(e.to_string()))
+            }
+        })
+    }
+
+    /// Refreshes the token `ms` milliseconds before it expires, instead of [`DEFAULT_REFRESH_AHEAD_MS`]
+    pub fn with_refresh_ahead(self, ms: u32) -> Self {
+        self.inner.refresh_ahead_ms.set(ms);
+        self
+    }
+
+    /// Stores a token obtained some other way, and schedules its refresh
+    ///
+    /// # Arguments
+    /// * `access_token` - The access token.
+    /// * `expires_at` - When the token expires, in milliseconds since the unix epoch.
+    pub fn set_token(&self, access_token: &str, expires_at: u64) {
+        *self.inner.token.borrow_mut() = Some((access_token.into(), expires_at));
+        let generation = self.inner.generation.get().wrapping_add(1);
+        self.inner.generation.set(generation);
+        self.schedule(generation);
+    }
+
+    /// The cached access token, even if it is about to expire
+    pub fn token(&self) -> Option<String> {
+        self.inner
+            .token
+            .borrow()
+            .as_ref()
+            .map(|(token, _)| token.clone())
+    }
+
+    /// When the cached token expires, in milliseconds since the unix epoch
+    pub fn expires_at(&self) -> Option<u64> {
+        self.inner
+            .token
+            .borrow()
+            .as_ref()
+            .map(|(_, expires_at)| *expires_at)
+    }
+
+    /// True if there is no token, or it expires in less than the refresh ahead time
+    pub fn needs_refresh(&self) -> bool {
+        match self.expires_at() {
+            Some(expires_at) => {
+                expires_at as f64 - crate::now_ms() < f64::from(self.inner.refresh_ahead_ms.get())
+            }
+            None => true,
+        }
+    }
+
+    /// How long until the token is refreshed in the background, `None` if there is no token.
+    /// It's `refresh_ahead_ms` before the token expires, but at least half of the time the token has left
+    /// and [`MIN_REFRESH_DELAY_MS`], so a token that lives shorter than the refresh ahead time isn't refreshed in a loop.
+    pub fn refresh_delay_ms(&self) -> Option<u32> {
+        let remaining = self.expires_at()? as f64 - crate::now_ms();
+        let delay = (remaining - f64::from(self.inner.refresh_ahead_ms.get()))
+            .max(remaining / 2.0)
+            .max(f64::from(MIN_REFRESH_DELAY_MS));
+        Some(delay.min(f64::from(u32::MAX)) as u32)
+    }
+
+    /// Fetches a new token. If a refresh is already running this waits for it instead of starting another one.
+    ///
+    /// # Response
+    /// The new access token. Emits [`Event::TokenRefreshed`] or [`Event::TokenRefreshFailed`].
+    pub async fn refresh(&self) -> Result<String, SdkError> {
+        let pending = self.inner.pending.borrow().clone();
+        let pending = match pending {
+            Some(pending) => pending,
+            None => {
+                let cache = self.clone();
+                let pending = async move { cache.run_refresh().await }
+                    .boxed_local()
+                    .shared();
+                *self.inner.pending.borrow_mut() = Some(pending.clone());
+                pending
+            }
+        };
+        pending.await
+    }
+
+    /// The closure to pass to [`init`](crate::init) as `oauth`.
+    /// It returns the cached token right away, and starts a refresh in the background if it's about to expire.
+    /// There is no background outside of the browser, call [`refresh`](TokenCache::refresh) there.
+    ///
+    /// The SDK can't wait for the token, so await [`refresh`](TokenCache::refresh) or call [`set_token`](TokenCache::set_token) before `connect`.
+    /// On an empty cache it returns an empty string, and the SDK emits `authentication_error`.
+    pub fn provider(&self) -> impl FnMut() -> String + 'static {
+        let cache = self.clone();
+        move || {
+            if cache.needs_refresh() && crate::ensure_event_loop().is_ok() {
+                let cache = cache.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let _ = cache.refresh().await;
+                });
+            }
+            cache.token().unwrap_or_default()
+        }
+    }
+
+    async fn run_refresh(&self) -> Result<String, SdkError> {
+        let result = (self.inner.refresh)().await;
+        *self.inner.pending.borrow_mut() = None;
+        match result {
+            Ok(token) => {
+                let expires_at = crate::now_ms() as u64 + u64::from(token.expires_in) * 1000;
+                self.set_token(&token.access_token, expires_at);
+                emit(Event::TokenRefreshed { expires_at });
+                Ok(token.access_token)
+            }
+            Err(error) => {
+                emit(Event::TokenRefreshFailed {
+                    error: error.clone(),
+                });
+                Err(error)
+            }
+        }
+    }
+
+    /// Refreshes the token after [`refresh_delay_ms`](TokenCache::refresh_delay_ms), unless another token is stored until then
+    fn schedule(&self, generation: u32) {
+        // there is no background outside of the browser
+        if crate::ensure_event_loop().is_err() {
+            return;
+        }
+        let Some(delay) = self.refresh_delay_ms() else {
+            return;
+        };
+        // the timer only keeps a weak reference, so dropping every clone of the cache stops it
+        let inner = Rc::downgrade(&self.inner);
+        wasm_bindgen_futures::spawn_local(async move {
+            crate::sleep(delay).await;
+            if let Some(inner) = inner.upgrade() {
+                if inner.generation.get() == generation {
+                    let _ = TokenCache { inner }.refresh().await;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,74 @@
+//! Keeps the latest `player_state_changed` payload, and lets commands wait for the state that proves they took effect
+use std::cell::RefCell;
+
+use futures::{
+    channel::oneshot,
+    future::{select, Either},
+};
+
+use crate::{error::SdkError, structs::state_change::StateChange};
+
+struct Waiter {
+    predicate: Box<dyn Fn(&StateChange) -> bool>,
+    sender: oneshot::Sender<StateChange>,
+}
+
+thread_local! {
+    static LAST_STATE: RefCell<Option<StateChange>> = const { RefCell::new(None) };
+    static WAITERS: RefCell<Vec<Waiter>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Called with every `player_state_changed` payload, `None` when playback moved to another device
+pub(crate) fn on_state_changed(state: Option<StateChange>) {
+    if let Some(state) = &state {
+        let matched: Vec<Waiter> = WAITERS.with(|waiters| {
+            let mut waiters = waiters.borrow_mut();
+            waiters.retain(|waiter| !waiter.sender.is_canceled());
+            let (matched, pending) = waiters
+                .drain(..)
+                .partition(|waiter| (waiter.predicate)(state));
+            *waiters = pending;
+            matched
+        });
+        for waiter in matched {
+            let _ = waiter.sender.send(state.clone());
+        }
+    }
+    LAST_STATE.with(|last| *last.borrow_mut() = state);
+}
+
+/// The latest `player_state_changed` payload, `None` if there is no playback on this device
+pub(crate) fn last_state() -> Option<StateChange> {
+    LAST_STATE.with(|last| last.borrow().clone())
+}
+
+/// A state the player is expected to reach, created before sending the command so a fast event isn't missed
+pub(crate) struct Expectation {
+    receiver: oneshot::Receiver<StateChange>,
+}
+
+/// Starts waiting for the first state change that matches `predicate`
+pub(crate) fn expect<P>(predicate: P) -> Expectation
+where
+    P: Fn(&StateChange) -> bool + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    WAITERS.with(|waiters| {
+        waiters.borrow_mut().push(Waiter {
+            predicate: Box::new(predicate),
+            sender,
+        })
+    });
+    Expectation { receiver }
+}
+
+impl Expectation {
+    /// Resolves with the matching state, or fails with [`SdkError::Timeout`] after `timeout_ms` milliseconds
+    pub(crate) async fn wait(self, timeout_ms: u32) -> Result<StateChange, SdkError> {
+        let timeout = Box::pin(crate::sleep(timeout_ms));
+        match select(self.receiver, timeout).await {
+            Either::Left((Ok(state), _)) => Ok(state),
+            _ => Err(SdkError::Timeout),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! An opt-in watchdog that notices when the SDK says it's playing but the position doesn't move,
+//! or when a track keeps loading forever, and optionally tries to get playback going again.
+//!
+//! The SDK doesn't emit state changes while a track plays normally, so the watchdog polls
+//! [`get_current_state`](crate::get_current_state) every `check_interval_ms` and hands it to a [`Checker`].
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    error::SdkError,
+    events::{emit, Event},
+    structs::web_playback::State,
+};
+
+/// What the watchdog noticed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StallReason {
+    /// The player is not paused, but the position didn't change for `stalled_after_ms`
+    NotProgressing,
+    /// The player was loading for more than `loading_after_ms`
+    Loading,
+}
+
+/// A step of the recovery sequence, every step runs when the stall outlasts the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecoveryStep {
+    /// Seek to the current position
+    Seek,
+    /// Pause then resume
+    PauseResume,
+    /// Disconnect then connect again
+    Reconnect,
+}
+
+/// The options of [`start`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogOptions {
+    /// How often the state is checked.
+    pub check_interval_ms: u32,
+    /// Playing without progress for this long is a stall.
+    pub stalled_after_ms: u32,
+    /// Loading for this long is a stall.
+    pub loading_after_ms: u32,
+    /// Run the recovery sequence (seek, pause/resume, reconnect) when a stall is detected.
+    pub recover: bool,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        WatchdogOptions {
+            check_interval_ms: 1000,
+            stalled_after_ms: 5000,
+            loading_after_ms: 10_000,
+            recover: false,
+        }
+    }
+}
+
+/// The running watchdog, it keeps running when this is dropped, call [`stop`](Watchdog::stop) to stop it
+#[derive(Clone)]
+pub struct Watchdog {
+    stopped: Rc<Cell<bool>>,
+}
+
+impl Watchdog {
+    /// Stops the watchdog after the current check
+    pub fn stop(&self) {
+        self.stopped.set(true);
+    }
+}
+
+/// Starts watching the playback, call it once the player exists, for example in the `on_ready` closure of `init`.
+///
+/// # Errors
+/// [`SdkError::PlayerNotReady`] if the player doesn't exist yet,
+/// [`SdkError::NotInBrowser`] on native targets, with the [`SimulatedPlayer`](crate::simulated::SimulatedPlayer) too.
+pub fn start(options: WatchdogOptions) -> Result<Watchdog, SdkError> {
+    crate::ensure_ready()?;
+    crate::ensure_event_loop()?;
+    let stopped = Rc::new(Cell::new(false));
+    let watchdog = Watchdog {
+        stopped: stopped.clone(),
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        let mut checker = Checker::new(options.clone());
+        while !stopped.get() {
+            crate::sleep(options.check_interval_ms).await;
+            if stopped.get() {
+                break;
+            }
+            let state = crate::get_current_state().await.ok().flatten();
+            if let Some(stall) = checker.check(crate::now_ms(), state.as_ref()) {
+                if let Some(step) = stall.step {
+                    recover(step, stall.position_ms).await;
+                }
+            }
+        }
+    });
+    Ok(watchdog)
+}
+
+/// A stall [`Checker::check`] noticed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stall {
+    pub reason: StallReason,
+    /// The position of the state that showed the stall
+    pub position_ms: i32,
+    /// The recovery step to run, `None` if `recover` is off or every step was tried
+    pub step: Option<RecoveryStep>,
+}
+
+/// Decides when the playback stalled and what to try next, from the states it's given.
+/// The watchdog feeds it [`get_current_state`](crate::get_current_state) every `check_interval_ms`,
+/// drive it yourself to test how your app reacts to stalls.
+#[derive(Debug, Clone)]
+pub struct Checker {
+    options: WatchdogOptions,
+    /// The last position that was different from the one before, and when it was seen
+    progress: Option<(i32, f64)>,
+    /// When the player started loading
+    loading_since: Option<f64>,
+    /// The last recovery step, reset when playback moves again
+    last_step: Option<RecoveryStep>,
+}
+
+impl Checker {
+    pub fn new(options: WatchdogOptions) -> Self {
+        Checker {
+            options,
+            progress: None,
+            loading_since: None,
+            last_step: None,
+        }
+    }
+
+    /// Looks at the state polled at `now_ms`, `None` if there is no playback on this device.
+    ///
+    /// # Response
+    /// The stall if there is one, after emitting [`Event::PlaybackStalled`] and [`Event::StallRecovery`] for its step.
+    /// A stall that lasts is reported again every `stalled_after_ms` (`loading_after_ms` while loading), with the next step.
+    pub fn check(&mut self, now_ms: f64, state: Option<&State>) -> Option<Stall> {
+        let Some(state) = state else {
+            *self = Checker::new(self.options.clone());
+            return None;
+        };
+
+        let reason = if state.loading {
+            let since = *self.loading_since.get_or_insert(now_ms);
+            (now_ms - since >= f64::from(self.options.loading_after_ms)).then(|| {
+                self.loading_since = Some(now_ms);
+                StallReason::Loading
+            })?
+        } else {
+            self.loading_since = None;
+            match self.progress {
+                Some((position, since)) if !state.paused && position == state.position => {
+                    (now_ms - since >= f64::from(self.options.stalled_after_ms)).then(|| {
+                        self.progress = Some((position, now_ms));
+                        StallReason::NotProgressing
+                    })?
+                }
+                _ => {
+                    self.progress = Some((state.position, now_ms));
+                    self.last_step = None;
+                    return None;
+                }
+            }
+        };
+
+        emit(Event::PlaybackStalled {
+            reason,
+            position_ms: state.position,
+        });
+        let step = if self.options.recover {
+            match self.last_step {
+                None => Some(RecoveryStep::Seek),
+                Some(RecoveryStep::Seek) => Some(RecoveryStep::PauseResume),
+                Some(RecoveryStep::PauseResume) => Some(RecoveryStep::Reconnect),
+                // everything was tried, wait for the playback to move again
+                Some(RecoveryStep::Reconnect) => None,
+            }
+        } else {
+            None
+        };
+        if let Some(step) = step {
+            self.last_step = Some(step);
+            emit(Event::StallRecovery { step });
+        }
+        Some(Stall {
+            reason,
+            position_ms: state.position,
+            step,
+        })
+    }
+}
+
+async fn recover(step: RecoveryStep, position_ms: i32) {
+    match step {
+        RecoveryStep::Seek => {
+            let _ = crate::seek(position_ms.max(0) as u32).await;
+        }
+        RecoveryStep::PauseResume => {
+            let _ = crate::pause().await;
+            let _ = crate::resume().await;
+        }
+        RecoveryStep::Reconnect => {
+            let _ = crate::disconnect();
+            let _ = crate::connect().await;
+        }
+    }
+}
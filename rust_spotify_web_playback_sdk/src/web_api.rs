@@ -0,0 +1,554 @@
+//! A small client for the `/me/player` endpoints of the Spotify Web API, enabled with the `web_api` feature.
+//!
+//! The Web Playback SDK can only control what is already playing, starting a playlist, transferring playback to our device,
+//! changing shuffle/repeat and the queue has to go through the Web API.
+//! By default the client uses the same token closure that was passed to [`init`](crate::init).
+use std::{cell::RefCell, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::SdkError,
+    events::{emit, Event},
+    structs::{Album, Artist, Image, LinkedFrom, RepeatMode, Track},
+    OAuthProvider,
+};
+
+/// The url of the Spotify Web API
+pub const BASE_URL: &str = "https://api.spotify.com/v1";
+
+/// A Spotify Web API client, cloning it is cheap
+#[derive(Clone)]
+pub struct WebApi {
+    base_url: String,
+    token: OAuthProvider,
+    http: reqwest::Client,
+}
+
+/// The body of the start/resume playback request, set either `context_uri` or `uris`
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct StartPlayback {
+    /// Spotify URI of the context to play, valid contexts are albums, artists & playlists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_uri: Option<String>,
+    /// The Spotify track URIs to play
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uris: Option<Vec<String>>,
+    /// Where in the context playback should start, only available with `context_uri` or `uris`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<Offset>,
+    /// The position_ms to start the first track at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_ms: Option<u32>,
+}
+
+/// Where playback should start in a context
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Offset {
+    /// Zero based index of the track in the context
+    Position(u32),
+    /// URI of the track in the context
+    Uri(String),
+}
+
+/// What [`play`](crate::play) should play on this device
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayRequest {
+    /// An album, artist or playlist, like `spotify:playlist:37i9dQZF1DXcBWIGoYBM5M`
+    Context {
+        uri: String,
+        /// Where in the context playback should start, `None` for the beginning
+        offset: Option<Offset>,
+        /// The position_ms to start the first track at
+        position_ms: Option<u32>,
+    },
+    /// A list of tracks, played in order starting with the first one
+    Tracks {
+        uris: Vec<String>,
+        /// The position_ms to start the first track at
+        position_ms: Option<u32>,
+    },
+}
+
+impl From<PlayRequest> for StartPlayback {
+    fn from(request: PlayRequest) -> Self {
+        match request {
+            PlayRequest::Context {
+                uri,
+                offset,
+                position_ms,
+            } => StartPlayback {
+                context_uri: Some(uri),
+                offset,
+                position_ms,
+                ..Default::default()
+            },
+            PlayRequest::Tracks { uris, position_ms } => StartPlayback {
+                uris: Some(uris),
+                position_ms,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// The user's queue
+#[derive(Debug)]
+pub struct Queue {
+    /// The currently playing track or episode
+    pub currently_playing: Option<Track>,
+    /// The tracks or episodes in the queue
+    pub queue: Vec<Track>,
+}
+
+/// A Spotify Connect device
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Device {
+    /// The device id, this can be null
+    pub id: Option<String>,
+    pub is_active: bool,
+    pub is_private_session: bool,
+    /// If true, no Web API commands will be accepted by this device
+    pub is_restricted: bool,
+    pub name: String,
+    /// Device type, such as "computer", "smartphone" or "speaker"
+    #[serde(rename = "type")]
+    pub device_type: String,
+    pub volume_percent: Option<u32>,
+    #[serde(default)]
+    pub supports_volume: bool,
+}
+
+/// The current user's profile, from `/me`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct User {
+    pub id: String,
+    pub display_name: Option<String>,
+    /// Only with the `user-read-email` scope
+    pub email: Option<String>,
+    /// The subscription level: "premium", "free" or "open". Only with the `user-read-private` scope
+    pub product: Option<String>,
+    /// ISO 3166-1 alpha-2 country code. Only with the `user-read-private` scope
+    pub country: Option<String>,
+}
+
+/// What [`preflight`] or [`preflight_with_scopes`] found out about a token, before `init` is called
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreflightReport {
+    /// The scopes in [`SCOPES`](crate::SCOPES) the token doesn't have
+    pub missing_scopes: Vec<String>,
+    /// The account type: "premium", "free" or "open", `None` without the `user-read-private` scope
+    pub product: Option<String>,
+    /// The country of the account, `None` without the `user-read-private` scope
+    pub country: Option<String>,
+}
+
+impl PreflightReport {
+    /// True if the account has Spotify Premium, the Web Playback SDK doesn't work without it
+    pub fn is_premium(&self) -> bool {
+        self.product.as_deref() == Some("premium")
+    }
+
+    /// True if the SDK should work with this token
+    pub fn is_ok(&self) -> bool {
+        self.missing_scopes.is_empty() && self.is_premium()
+    }
+}
+
+/// A track or an episode as the Web API returns it, it has a different shape than the SDK object
+#[derive(Deserialize)]
+struct ApiItem {
+    album: Option<Album>,
+    /// Only for episodes
+    show: Option<Album>,
+    #[serde(default)]
+    artists: Vec<Artist>,
+    duration_ms: u32,
+    id: Option<String>,
+    is_playable: Option<bool>,
+    linked_from: Option<LinkedFrom>,
+    name: String,
+    #[serde(rename = "type")]
+    item_type: String,
+    uri: String,
+    /// Only for episodes
+    images: Option<Vec<Image>>,
+}
+
+impl From<ApiItem> for Track {
+    fn from(item: ApiItem) -> Self {
+        let album = match (item.album, item.show) {
+            (Some(album), _) => album,
+            (None, Some(mut show)) => {
+                if show.images.is_empty() {
+                    show.images = item.images.unwrap_or_default();
+                }
+                show
+            }
+            (None, None) => Album {
+                uri: String::new(),
+                name: String::new(),
+                images: item.images.unwrap_or_default(),
+            },
+        };
+        let (media_type, track_type) = match item.item_type.as_str() {
+            "track" => ("audio", "audio"),
+            // the Web API has no video flag, its episodes and audiobook chapters are audio
+            "episode" | "chapter" => ("audio", item.item_type.as_str()),
+            // a type this crate doesn't know, so its media is unknown too
+            other => ("", other),
+        };
+        Track {
+            album,
+            artists: item.artists,
+            duration_ms: item.duration_ms,
+            id: item.id.unwrap_or_default(),
+            is_playable: item.is_playable.unwrap_or(true),
+            linked_from: item.linked_from,
+            media_type: media_type.into(),
+            metadata: None,
+            name: item.name,
+            track_type: track_type.into(),
+            uid: String::new(),
+            uri: item.uri,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct QueueResponse {
+    currently_playing: Option<ApiItem>,
+    #[serde(default)]
+    queue: Vec<ApiItem>,
+}
+
+#[derive(Deserialize)]
+struct DevicesResponse {
+    devices: Vec<Device>,
+}
+
+#[derive(Serialize)]
+struct TransferPlayback<'a> {
+    device_ids: [&'a str; 1],
+    play: bool,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorObject,
+}
+
+#[derive(Deserialize)]
+struct ErrorObject {
+    message: String,
+    reason: Option<String>,
+}
+
+impl WebApi {
+    /// Creates a client that gets its token from `token`
+    pub fn new<T>(token: T) -> Self
+    where
+        T: FnMut() -> String + 'static,
+    {
+        WebApi {
+            base_url: BASE_URL.into(),
+            token: Rc::new(RefCell::new(token)),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Creates a client that uses the token closure passed to [`init`](crate::init)
+    ///
+    /// # Errors
+    /// [`SdkError::PlayerNotReady`] if `init` was not called
+    pub fn from_init() -> Result<Self, SdkError> {
+        let token = crate::oauth_provider().ok_or(SdkError::PlayerNotReady)?;
+        Ok(WebApi {
+            base_url: BASE_URL.into(),
+            token,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Sends the requests to `base_url` instead of [`BASE_URL`], useful for proxies and mock servers
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').into();
+        self
+    }
+
+    /// Transfer playback to a new device.
+    ///
+    /// # Arguments
+    /// * `device_id` - The id of the device playback should be transferred to, for example the one from the `ready` event.
+    /// * `play` - true: ensure playback happens on the new device, false: keep the current playback state.
+    pub async fn transfer_playback(&self, device_id: &str, play: bool) -> Result<(), SdkError> {
+        let request = self
+            .http
+            .put(self.url("/me/player"))
+            .json(&TransferPlayback {
+                device_ids: [device_id],
+                play,
+            });
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Start a new context or resume current playback.
+    ///
+    /// # Arguments
+    /// * `device_id` - The device to target, if not supplied the user's currently active device is the target.
+    /// * `playback` - What to play, an empty `StartPlayback` resumes the current playback.
+    pub async fn start_playback(
+        &self,
+        device_id: Option<&str>,
+        playback: &StartPlayback,
+    ) -> Result<(), SdkError> {
+        let request = self
+            .http
+            .put(self.url("/me/player/play"))
+            .query(&device_query(device_id))
+            .json(playback);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Toggle shuffle on or off for the user’s playback.
+    ///
+    /// # Arguments
+    /// * `state` - true: shuffle the user's playback, false: do not shuffle the user's playback.
+    /// * `device_id` - The device to target, if not supplied the user's currently active device is the target.
+    pub async fn set_shuffle(&self, state: bool, device_id: Option<&str>) -> Result<(), SdkError> {
+        let mut query = device_query(device_id);
+        query.push(("state", if state { "true" } else { "false" }));
+        let request = self.http.put(self.url("/me/player/shuffle")).query(&query);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Set the repeat mode for the user's playback.
+    ///
+    /// # Arguments
+    /// * `mode` - The new repeat mode.
+    /// * `device_id` - The device to target, if not supplied the user's currently active device is the target.
+    pub async fn set_repeat(
+        &self,
+        mode: RepeatMode,
+        device_id: Option<&str>,
+    ) -> Result<(), SdkError> {
+        let mut query = device_query(device_id);
+        query.push(("state", mode.as_str()));
+        let request = self.http.put(self.url("/me/player/repeat")).query(&query);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Add an item to the end of the user's current playback queue.
+    ///
+    /// # Arguments
+    /// * `uri` - The uri of the track or episode to add to the queue.
+    /// * `device_id` - The device to target, if not supplied the user's currently active device is the target.
+    pub async fn add_to_queue(&self, uri: &str, device_id: Option<&str>) -> Result<(), SdkError> {
+        let mut query = device_query(device_id);
+        query.push(("uri", uri));
+        let request = self.http.post(self.url("/me/player/queue")).query(&query);
+        self.send(request).await.map(|_| ())
+    }
+
+    /// Get the list of objects that make up the user's queue.
+    pub async fn get_queue(&self) -> Result<Queue, SdkError> {
+        let request = self.http.get(self.url("/me/player/queue"));
+        let response: QueueResponse = self.send_json(request).await?;
+        Ok(Queue {
+            currently_playing: response.currently_playing.map(Track::from),
+            queue: response.queue.into_iter().map(Track::from).collect(),
+        })
+    }
+
+    /// Get information about the user’s available Spotify Connect devices.
+    pub async fn get_devices(&self) -> Result<Vec<Device>, SdkError> {
+        let request = self.http.get(self.url("/me/player/devices"));
+        let response: DevicesResponse = self.send_json(request).await?;
+        Ok(response.devices)
+    }
+
+    /// Get detailed profile information about the current user.
+    pub async fn get_me(&self) -> Result<User, SdkError> {
+        let request = self.http.get(self.url("/me"));
+        self.send_json(request).await
+    }
+
+    /// Checks the account type of the token, and guesses the missing scopes from the profile, see [`preflight`].
+    pub async fn preflight(&self) -> Result<PreflightReport, SdkError> {
+        let user = self.get_me().await?;
+        // the profile only shows these two, the others can't be checked without playing something
+        let missing_scopes = [
+            ("user-read-email", user.email.is_none()),
+            ("user-read-private", user.product.is_none()),
+        ]
+        .into_iter()
+        .filter(|(_, missing)| *missing)
+        .map(|(scope, _)| scope.to_string())
+        .collect();
+        Ok(PreflightReport {
+            missing_scopes,
+            product: user.product,
+            country: user.country,
+        })
+    }
+
+    /// Checks the scopes and the account type of the token, see [`preflight_with_scopes`].
+    ///
+    /// # Arguments
+    /// * `granted_scopes` - The space separated `scope` of the token response.
+    pub async fn preflight_with_scopes(
+        &self,
+        granted_scopes: &str,
+    ) -> Result<PreflightReport, SdkError> {
+        let user = self.get_me().await?;
+        let granted_scopes: Vec<&str> = granted_scopes.split_whitespace().collect();
+        Ok(PreflightReport {
+            missing_scopes: crate::SCOPES
+                .iter()
+                .filter(|scope| !granted_scopes.contains(scope))
+                .map(|scope| scope.to_string())
+                .collect(),
+            product: user.product,
+            country: user.country,
+        })
+    }
+
+    /// Transfers playback to `device_id`, retrying while the Web API doesn't know about the device yet,
+    /// and emits [`Event::DeviceActivated`] or [`Event::ActivationFailed`]. This is what `InitOptions::auto_activate` does after the `ready` event.
+    ///
+    /// # Arguments
+    /// * `device_id` - The id of the device from the `ready` event.
+    /// * `options` - Whether to play and how to retry, see [`AutoActivate`].
+    pub async fn activate(&self, device_id: &str, options: &AutoActivate) -> Result<(), SdkError> {
+        let mut retries = 0;
+        let result = loop {
+            match self.transfer_playback(device_id, options.play).await {
+                Err(SdkError::Api {
+                    status: 404 | 502, ..
+                }) if retries < options.max_retries => {
+                    retries += 1;
+                    crate::sleep(options.retry_delay_ms).await;
+                }
+                result => break result,
+            }
+        };
+        let device_id = device_id.to_string();
+        emit(match &result {
+            Ok(()) => Event::DeviceActivated { device_id },
+            Err(error) => Event::ActivationFailed {
+                device_id,
+                error: error.clone(),
+            },
+        });
+        result
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, SdkError> {
+        let token = (self.token.borrow_mut())();
+        let response = request
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| SdkError::Request(e.to_string()))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        Err(match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(ErrorResponse { error }) => SdkError::Api {
+                status,
+                message: error.message,
+                reason: error.reason,
+            },
+            Err(_) => SdkError::Api {
+                status,
+                message: body,
+                reason: None,
+            },
+        })
+    }
+
+    async fn send_json<T>(&self, request: reqwest::RequestBuilder) -> Result<T, SdkError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let body = self
+            .send(request)
+            .await?
+            .text()
+            .await
+            .map_err(|e| SdkError::Request(e.to_string()))?;
+        serde_json::from_str(&body).map_err(|e| SdkError::Deserialize(e.to_string()))
+    }
+}
+
+/// Checks a token before calling [`init`](crate::init), the SDK only reports missing scopes and free accounts
+/// later through the `authentication_error` and `account_error` events.
+///
+/// Spotify tokens are opaque, so the granted scopes can't be decoded from the token.
+/// `missing_scopes` is a guess from what `/me` returns: only `user-read-email` and `user-read-private` can be noticed,
+/// a token without `streaming` or `user-modify-playback-state` still looks fine.
+/// Use [`preflight_with_scopes`] when the `scope` of the token response is known.
+///
+/// # Arguments
+/// * `token` - The access token.
+pub async fn preflight(token: &str) -> Result<PreflightReport, SdkError> {
+    let token = token.to_string();
+    WebApi::new(move || token.clone()).preflight().await
+}
+
+/// Like [`preflight`], but checks every scope in [`SCOPES`](crate::SCOPES) against the ones the token was granted.
+///
+/// # Arguments
+/// * `token` - The access token.
+/// * `granted_scopes` - The space separated `scope` of the token response, like `Token::scope` of the `auth` feature.
+pub async fn preflight_with_scopes(
+    token: &str,
+    granted_scopes: &str,
+) -> Result<PreflightReport, SdkError> {
+    let token = token.to_string();
+    WebApi::new(move || token.clone())
+        .preflight_with_scopes(granted_scopes)
+        .await
+}
+
+/// How to transfer playback to this device after the `ready` event, see [`InitOptions::auto_activate`](crate::InitOptions::auto_activate)
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoActivate {
+    /// Start playing on this device after the transfer, instead of keeping the current playback state.
+    pub play: bool,
+    /// How many times to retry when the Web API doesn't know about the device yet (404 or 502).
+    pub max_retries: u32,
+    /// The milliseconds to wait between retries.
+    pub retry_delay_ms: u32,
+}
+
+impl Default for AutoActivate {
+    fn default() -> Self {
+        AutoActivate {
+            play: false,
+            max_retries: 5,
+            retry_delay_ms: 1000,
+        }
+    }
+}
+
+/// Transfers playback to `device_id` with the token closure passed to `init`, see [`WebApi::activate`]
+pub(crate) async fn activate(device_id: String, options: AutoActivate) {
+    match WebApi::from_init() {
+        Ok(api) => {
+            let _ = api.activate(&device_id, &options).await;
+        }
+        Err(error) => emit(Event::ActivationFailed { device_id, error }),
+    }
+}
+
+fn device_query(device_id: Option<&str>) -> Vec<(&'static str, &str)> {
+    device_id.map(|id| ("device_id", id)).into_iter().collect()
+}
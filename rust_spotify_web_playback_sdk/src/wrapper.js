@@ -95,6 +95,10 @@ function activateElement() {
     return player.activateElement();
 }
 
+function sleep(ms) {
+    return new Promise(resolve => setTimeout(resolve, ms));
+}
+
 export {
     init,
     player_ready,
@@ -115,5 +119,6 @@ export {
     seek,
     previousTrack,
     nextTrack,
-    activateElement
+    activateElement,
+    sleep
 };
\ No newline at end of file
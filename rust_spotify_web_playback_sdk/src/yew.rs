@@ -0,0 +1,228 @@
+//! Yew bindings, requires the `yew` feature.
+//! [`SpotifyPlayerProvider`] initializes the player once and re-renders its children with every new
+//! [`Snapshot`](crate::store::Snapshot) of the [`PlayerStore`](crate::store::PlayerStore), the hooks read it from the context.
+//!
+//! # Example
+//! ```rust,ignore
+//! use yew::prelude::*;
+//! use rust_spotify_web_playback_sdk::yew::*;
+//!
+//! #[function_component]
+//! fn App() -> Html {
+//!     let options = SpotifyOptions::new("example player", || "token".to_string());
+//!     html! {
+//!         <SpotifyPlayerProvider {options}>
+//!             <NowPlaying/>
+//!         </SpotifyPlayerProvider>
+//!     }
+//! }
+//!
+//! #[function_component]
+//! fn NowPlaying() -> Html {
+//!     let state = use_spotify_state();
+//!     let controls = use_spotify_controls();
+//!     html! {
+//!         <>
+//!             <p>{state.map(|state| state.track_window.current_track.name)}</p>
+//!             <button onclick={controls.toggle_play.reform(|_| ())}>{"play/pause"}</button>
+//!         </>
+//!     }
+//! }
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use ::yew::{
+    function_component, hook, html, use_context, use_effect_with, use_memo, use_state, Callback,
+    ContextProvider, Html, Properties,
+};
+
+use crate::{
+    command_queue::Command,
+    store::{player_store, Anchor, Lifecycle, Snapshot, POSITION_INTERVAL_MS},
+    structs::state_change::StateChange,
+    InitOptions,
+};
+
+/// The options of [`SpotifyPlayerProvider`], they are only used by the first provider that mounts
+#[derive(Clone)]
+pub struct SpotifyOptions {
+    oauth: Rc<RefCell<dyn FnMut() -> String>>,
+    /// The options passed to [`init_with_options`](crate::init_with_options).
+    pub init: InitOptions,
+    /// Call [`connect`](crate::connect) once the SDK is ready, true by default.
+    pub connect: bool,
+}
+
+impl SpotifyOptions {
+    /// The default options with the given player name
+    ///
+    /// # Arguments
+    /// * `name` - The name of the player.
+    /// * `oauth` - A closure that returns a String containing the Spotify OAuth token.
+    pub fn new<T>(name: &str, oauth: T) -> Self
+    where
+        T: FnMut() -> String + 'static,
+    {
+        SpotifyOptions {
+            oauth: Rc::new(RefCell::new(oauth)),
+            init: InitOptions::new(name),
+            connect: true,
+        }
+    }
+}
+
+impl PartialEq for SpotifyOptions {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.oauth, &other.oauth) && self.connect == other.connect
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SpotifyPlayerProviderProps {
+    pub options: SpotifyOptions,
+    #[prop_or_default]
+    pub children: Html,
+}
+
+/// What [`SpotifyPlayerProvider`] puts in the context
+#[derive(Debug, Clone, PartialEq)]
+struct SpotifyContext(Rc<Snapshot>);
+
+/// Initializes the player if it wasn't yet, and provides the latest snapshot to its children.
+/// The store subscription is removed when the provider unmounts.
+#[function_component]
+pub fn SpotifyPlayerProvider(props: &SpotifyPlayerProviderProps) -> Html {
+    let snapshot = use_state(|| Rc::new(player_store().get()));
+    {
+        let snapshot = snapshot.clone();
+        let options = props.options.clone();
+        // effects don't run during server side rendering
+        use_effect_with((), move |_| {
+            let store = player_store();
+            snapshot.set(Rc::new(store.get()));
+            let subscription =
+                store.subscribe(move |new_snapshot| snapshot.set(Rc::new(new_snapshot.clone())));
+            if store.get().lifecycle == Lifecycle::Uninitialized {
+                let oauth = options.oauth;
+                let connect = options.connect;
+                crate::init_with_options(
+                    move || (oauth.borrow_mut())(),
+                    move || {
+                        if connect {
+                            wasm_bindgen_futures::spawn_local(async {
+                                let _ = crate::connect().await;
+                            });
+                        }
+                    },
+                    options.init,
+                );
+            }
+            move || {
+                player_store().unsubscribe(subscription);
+            }
+        });
+    }
+    html! {
+        <ContextProvider<SpotifyContext> context={SpotifyContext((*snapshot).clone())}>
+            {props.children.clone()}
+        </ContextProvider<SpotifyContext>>
+    }
+}
+
+/// The latest snapshot of the closest [`SpotifyPlayerProvider`], panics if there is none
+#[hook]
+pub fn use_spotify_snapshot() -> Rc<Snapshot> {
+    use_context::<SpotifyContext>()
+        .expect("use_spotify_* hooks need a SpotifyPlayerProvider above them")
+        .0
+}
+
+/// The latest state, `None` if there is no playback on this device
+#[hook]
+pub fn use_spotify_state() -> Option<StateChange> {
+    use_spotify_snapshot().state.clone()
+}
+
+/// Where the player is in its life
+#[hook]
+pub fn use_spotify_lifecycle() -> Lifecycle {
+    use_spotify_snapshot().lifecycle
+}
+
+/// The position in milliseconds, interpolated between state changes while playing
+#[hook]
+pub fn use_spotify_position() -> i32 {
+    let state = use_spotify_state();
+    let position = use_state(|| Anchor::from_state(&state).position());
+    {
+        let position = position.clone();
+        let deps = state
+            .as_ref()
+            .map(|state| (state.position, state.duration, state.paused, state.loading));
+        use_effect_with(deps, move |_| {
+            let anchor = Anchor::from_state(&state);
+            position.set(anchor.position());
+            let stopped = Rc::new(Cell::new(false));
+            if anchor.playing {
+                let stopped = stopped.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    loop {
+                        crate::sleep(POSITION_INTERVAL_MS).await;
+                        if stopped.get() {
+                            break;
+                        }
+                        position.set(anchor.position());
+                    }
+                });
+            }
+            move || stopped.set(true)
+        });
+    }
+    *position
+}
+
+/// Callbacks that send commands through the [`PlayerStore`](crate::store::PlayerStore), so the state updates right away.
+/// A command that fails is rolled back and reported with [`Event::OptimisticRollback`](crate::events::Event::OptimisticRollback).
+#[derive(Clone, PartialEq)]
+pub struct SpotifyControls {
+    pub pause: Callback<()>,
+    pub resume: Callback<()>,
+    pub toggle_play: Callback<()>,
+    pub next_track: Callback<()>,
+    pub previous_track: Callback<()>,
+    /// Seek to the position in milliseconds
+    pub seek: Callback<u32>,
+    /// Set the volume between 0 and 1
+    pub set_volume: Callback<f32>,
+    /// See [`activate_element`](crate::activate_element), call it from a click handler
+    pub activate_element: Callback<()>,
+}
+
+/// The [`SpotifyControls`], the same callbacks on every render
+#[hook]
+pub fn use_spotify_controls() -> SpotifyControls {
+    (*use_memo((), |_| SpotifyControls {
+        pause: Callback::from(|_| send(Command::Pause)),
+        resume: Callback::from(|_| send(Command::Resume)),
+        toggle_play: Callback::from(|_| send(Command::TogglePlay)),
+        next_track: Callback::from(|_| send(Command::NextTrack)),
+        previous_track: Callback::from(|_| send(Command::PreviousTrack)),
+        seek: Callback::from(|position_ms| send(Command::Seek(position_ms))),
+        set_volume: Callback::from(|volume| send(Command::SetVolume(volume))),
+        activate_element: Callback::from(|_| {
+            wasm_bindgen_futures::spawn_local(async {
+                let _ = crate::activate_element().await;
+            })
+        }),
+    }))
+    .clone()
+}
+
+fn send(command: Command) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = player_store().send(command).await;
+    });
+}
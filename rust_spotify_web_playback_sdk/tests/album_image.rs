@@ -0,0 +1,40 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use rust_spotify_web_playback_sdk::structs::{Album, Image};
+
+fn image(width: Option<i32>) -> Image {
+    Image {
+        url: format!("https://i.scdn.co/image/{}", width.unwrap_or_default()),
+        size: None,
+        width,
+        height: width,
+    }
+}
+
+fn album(widths: &[Option<i32>]) -> Album {
+    Album {
+        uri: "spotify:album:1".into(),
+        name: "Album".into(),
+        images: widths.iter().copied().map(image).collect(),
+    }
+}
+
+#[test]
+fn picks_the_smallest_image_that_is_large_enough() {
+    let album = album(&[Some(640), Some(64), Some(300)]);
+    assert_eq!(album.image(200).unwrap().width, Some(300));
+    assert_eq!(album.image(300).unwrap().width, Some(300));
+    assert_eq!(album.image(10).unwrap().width, Some(64));
+}
+
+#[test]
+fn falls_back_to_the_widest_image() {
+    let album = album(&[Some(64), None, Some(300)]);
+    assert_eq!(album.image(1000).unwrap().width, Some(300));
+}
+
+#[test]
+fn images_without_a_width_are_a_last_resort() {
+    assert_eq!(album(&[None]).image(300).unwrap().width, None);
+    assert!(album(&[]).image(300).is_none());
+}
@@ -0,0 +1,139 @@
+#![cfg(all(feature = "auth", not(target_arch = "wasm32")))]
+
+use mockito::Matcher;
+use rust_spotify_web_playback_sdk::{
+    auth::{code_challenge, parse_callback, CallbackParams, PkceAuth, TokenStorage},
+    backend,
+    error::SdkError,
+    simulated::SimulatedPlayer,
+};
+
+fn auth(server: &mockito::ServerGuard) -> PkceAuth {
+    PkceAuth::new("client", "http://localhost:8080/callback")
+        .with_storage(TokenStorage::Memory)
+        .with_urls(
+            &format!("{}/authorize", server.url()),
+            &format!("{}/api/token", server.url()),
+        )
+}
+
+#[test]
+fn code_challenge_is_base64url_sha256() {
+    assert_eq!(
+        code_challenge("dBjftJeZ4CVP-mJ92K9bZDjO-Ow4rPEPzpqDCOkQmZ4"),
+        "ijc6r2M-3WPyc4qekAV7dsTU4Qn8QQqZExn0l9GSel8"
+    );
+}
+
+#[test]
+fn authorize_url_has_the_sdk_scopes() {
+    let url = PkceAuth::new("client", "http://localhost:8080/callback")
+        .authorize_url("challenge", "state");
+    assert!(url.starts_with("https://accounts.spotify.com/authorize?"));
+    assert!(url.contains("client_id=client"));
+    assert!(url.contains("response_type=code"));
+    assert!(url.contains("redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fcallback"));
+    assert!(url
+        .contains("scope=streaming+user-read-email+user-read-private+user-modify-playback-state"));
+    assert!(url.contains("code_challenge_method=S256"));
+    assert!(url.contains("code_challenge=challenge"));
+    assert!(url.contains("state=state"));
+}
+
+#[test]
+fn callback_parameters() {
+    assert_eq!(
+        parse_callback("?code=abc&state=xyz").unwrap(),
+        Some(CallbackParams {
+            code: "abc".into(),
+            state: "xyz".into(),
+        })
+    );
+    assert_eq!(parse_callback("").unwrap(), None);
+    assert_eq!(
+        parse_callback("?error=access_denied&state=xyz"),
+        Err(SdkError::Auth("access_denied".into()))
+    );
+}
+
+#[tokio::test]
+async fn exchange_code_and_refresh() {
+    let mut server = mockito::Server::new_async().await;
+    let exchange = server
+        .mock("POST", "/api/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "authorization_code".into()),
+            Matcher::UrlEncoded("code".into(), "abc".into()),
+            Matcher::UrlEncoded("code_verifier".into(), "verifier".into()),
+            Matcher::UrlEncoded("client_id".into(), "client".into()),
+            Matcher::UrlEncoded(
+                "redirect_uri".into(),
+                "http://localhost:8080/callback".into(),
+            ),
+        ]))
+        .with_status(200)
+        .with_body(
+            r#"{"access_token":"access","token_type":"Bearer","scope":"streaming user-read-email","expires_in":3600,"refresh_token":"refresh"}"#,
+        )
+        .create_async()
+        .await;
+    let refresh = server
+        .mock("POST", "/api/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".into(), "refresh_token".into()),
+            Matcher::UrlEncoded("refresh_token".into(), "refresh".into()),
+        ]))
+        .with_status(200)
+        .with_body(
+            r#"{"access_token":"new access","token_type":"Bearer","scope":"streaming","expires_in":3600}"#,
+        )
+        .create_async()
+        .await;
+
+    let auth = auth(&server);
+    let token = auth.exchange_code("abc", "verifier").await.unwrap();
+    assert_eq!(token.access_token, "access");
+    assert!(!token.expires_soon());
+    assert_eq!(auth.token(), Some(token));
+    assert_eq!((auth.token_provider())(), "access");
+
+    let token = auth.refresh().await.unwrap();
+    assert_eq!(token.access_token, "new access");
+    assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+    assert_eq!(auth.token(), Some(token));
+
+    exchange.assert_async().await;
+    refresh.assert_async().await;
+
+    auth.logout();
+    assert_eq!(auth.token(), None);
+}
+
+#[tokio::test]
+async fn token_endpoint_errors() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/api/token")
+        .with_status(400)
+        .with_body(r#"{"error":"invalid_grant","error_description":"Invalid authorization code"}"#)
+        .create_async()
+        .await;
+
+    assert_eq!(
+        auth(&server).exchange_code("expired", "verifier").await,
+        Err(SdkError::Api {
+            status: 400,
+            message: "Invalid authorization code".into(),
+            reason: Some("invalid_grant".into()),
+        })
+    );
+}
+
+#[tokio::test]
+async fn the_redirects_need_a_browser_even_with_the_simulated_player() {
+    backend::set_backend(SimulatedPlayer::new(vec![]));
+    let auth = PkceAuth::new("client", "http://localhost:8080/callback")
+        .with_storage(TokenStorage::Memory);
+    assert_eq!(auth.login(), Err(SdkError::NotInBrowser));
+    assert_eq!(auth.handle_callback().await, Err(SdkError::NotInBrowser));
+}
@@ -0,0 +1,86 @@
+//! The logic of the Leptos components, run it with `cargo test --features components --test components`.
+#![cfg(all(not(target_arch = "wasm32"), feature = "components"))]
+
+use std::{cell::RefCell, fs, rc::Rc};
+
+use leptos::{create_action, create_runtime};
+use rust_spotify_web_playback_sdk::{
+    components::{EnabledControls, RangeDrag},
+    structs::state_change::StateChange,
+};
+
+fn state_change(name: &str) -> StateChange {
+    let path = format!(
+        "{}/tests/fixtures/state_change/{name}.json",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn the_controls_follow_the_capabilities() {
+    assert_eq!(
+        EnabledControls::from_state(Some(&state_change("track"))),
+        EnabledControls {
+            previous: true,
+            play_pause: true,
+            next: true,
+            seek: true,
+        }
+    );
+    // an ad can only be paused
+    assert_eq!(
+        EnabledControls::from_state(Some(&state_change("ad"))),
+        EnabledControls {
+            previous: false,
+            play_pause: true,
+            next: false,
+            seek: false,
+        }
+    );
+    // paused, so play/pause resumes, which is allowed even though pausing isn't
+    assert_eq!(
+        EnabledControls::from_state(Some(&state_change("restricted"))),
+        EnabledControls {
+            previous: true,
+            play_pause: true,
+            next: false,
+            seek: true,
+        }
+    );
+    assert_eq!(
+        EnabledControls::from_state(None),
+        EnabledControls::default()
+    );
+}
+
+#[test]
+fn a_dragged_range_only_dispatches_when_released() {
+    let runtime = create_runtime();
+    let dispatched = Rc::new(RefCell::new(Vec::new()));
+    let drag = RangeDrag::new(create_action({
+        let dispatched = dispatched.clone();
+        move |position_ms: &u32| {
+            dispatched.borrow_mut().push(*position_ms);
+            async { Ok(()) }
+        }
+    }));
+
+    assert_eq!(drag.value(|| 1000), 1000);
+    drag.input("40000");
+    drag.input("61234");
+    // the thumb stays under the pointer while the position moves on
+    assert_eq!(drag.value(|| 2000), 61234);
+    assert!(dispatched.borrow().is_empty());
+
+    drag.change("61234", |position_ms| position_ms as u32);
+    assert_eq!(*dispatched.borrow(), [61234]);
+    assert_eq!(drag.value(|| 3000), 3000);
+
+    // a value that isn't a number sends nothing and stops the drag
+    drag.input("5000");
+    drag.change("", |position_ms| position_ms as u32);
+    assert_eq!(*dispatched.borrow(), [61234]);
+    assert_eq!(drag.value(|| 4000), 4000);
+    runtime.dispose();
+}
@@ -0,0 +1,71 @@
+//! The `<spotify-player>` element against a fake `Spotify.Player` and a fake custom elements registry in Node,
+//! run it with `cargo test --target wasm32-unknown-unknown --features custom_element --test custom_element`.
+#![cfg(all(target_arch = "wasm32", feature = "custom_element"))]
+
+use rust_spotify_web_playback_sdk::{
+    custom_element::{self, TAG_NAME},
+    prelude as sp,
+    store::{player_store, Lifecycle},
+};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "/tests/support/fake_spotify.js")]
+extern "C" {
+    fn install();
+    fn tick() -> js_sys::Promise;
+    #[wasm_bindgen(js_name = playerName)]
+    fn player_name() -> Option<String>;
+    #[wasm_bindgen(js_name = takeCalls)]
+    fn take_calls() -> Vec<String>;
+    fn emit(event: &str, payload: &JsValue) -> u32;
+}
+
+#[wasm_bindgen(module = "/tests/support/fake_elements.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = installElements)]
+    fn install_elements();
+    #[wasm_bindgen(js_name = connectElement)]
+    fn connect_element(tag: &str, attributes: &JsValue) -> JsValue;
+    #[wasm_bindgen(js_name = dispatchedEvents)]
+    fn dispatched_events(element: &JsValue) -> Vec<String>;
+    #[wasm_bindgen(js_name = takeTokenRequests)]
+    fn take_token_requests() -> u32;
+}
+
+fn attributes(name: &str) -> JsValue {
+    let attributes = js_sys::Object::new();
+    js_sys::Reflect::set(&attributes, &"name".into(), &name.into()).unwrap();
+    js_sys::Reflect::set(
+        &attributes,
+        &"token-endpoint".into(),
+        &"https://example.com/token".into(),
+    )
+    .unwrap();
+    attributes.into()
+}
+
+#[wasm_bindgen_test]
+async fn elements_connected_back_to_back_share_one_player() {
+    install();
+    install_elements();
+    assert!(custom_element::define());
+    assert!(!custom_element::define());
+
+    let first = connect_element(TAG_NAME, &attributes("first"));
+    assert_eq!(player_store().get().lifecycle, Lifecycle::Initializing);
+    // the token of the first one is still loading
+    let second = connect_element(TAG_NAME, &attributes("second"));
+    // the first element connects the player once it's ready
+    while take_calls().is_empty() {
+        JsFuture::from(tick()).await.unwrap();
+    }
+    assert!(sp::player_ready());
+    assert_eq!(take_token_requests(), 1);
+    assert_eq!(player_name().as_deref(), Some("first"));
+
+    emit("autoplay_failed", &JsValue::UNDEFINED);
+    assert_eq!(dispatched_events(&first), ["autoplay_failed"]);
+    assert_eq!(dispatched_events(&second), ["autoplay_failed"]);
+}
@@ -0,0 +1,99 @@
+use rust_spotify_web_playback_sdk::{
+    error::{ErrorKind, Recovery},
+    structs::web_playback::Error,
+};
+
+fn kind(event: &str, message: &str) -> ErrorKind {
+    Error {
+        message: message.into(),
+    }
+    .kind(event)
+}
+
+#[test]
+fn known_sdk_messages() {
+    assert_eq!(
+        kind(
+            "initialization_error",
+            "Failed to initialize player: EME not supported"
+        ),
+        ErrorKind::UnsupportedBrowser
+    );
+    assert_eq!(
+        kind("initialization_error", "Failed to initialize player"),
+        ErrorKind::InitializationFailed
+    );
+    assert_eq!(
+        kind("authentication_error", "Invalid token scopes."),
+        ErrorKind::MissingScopes
+    );
+    assert_eq!(
+        kind("authentication_error", "Authentication failed"),
+        ErrorKind::InvalidToken
+    );
+    assert_eq!(
+        kind(
+            "account_error",
+            "This functionality is restricted to premium users only"
+        ),
+        ErrorKind::PremiumRequired
+    );
+    assert_eq!(
+        kind("playback_error", "Failed to perform playback"),
+        ErrorKind::PlaybackFailed
+    );
+    assert_eq!(
+        kind(
+            "playback_error",
+            "Cannot perform operation; no list was loaded."
+        ),
+        ErrorKind::NothingLoaded
+    );
+    assert_eq!(kind("not_an_error", "whatever"), ErrorKind::Unknown);
+}
+
+#[test]
+fn only_the_known_messages_pick_a_kind() {
+    // mentions the browser, but it's not about the browser
+    assert_eq!(
+        kind(
+            "initialization_error",
+            "Failed to initialize player: the browser tab was closed"
+        ),
+        ErrorKind::InitializationFailed
+    );
+    assert_eq!(
+        kind("initialization_error", "Unsupported audio format"),
+        ErrorKind::InitializationFailed
+    );
+    assert_eq!(
+        kind("authentication_error", "The token has no scope to refresh"),
+        ErrorKind::InvalidToken
+    );
+    // the case and the surrounding whitespace don't matter
+    assert_eq!(
+        kind(
+            "initialization_error",
+            " failed to initialize player: EME NOT SUPPORTED\n"
+        ),
+        ErrorKind::UnsupportedBrowser
+    );
+}
+
+#[test]
+fn recovery_hints() {
+    assert!(!ErrorKind::PremiumRequired.recoverable());
+    assert_eq!(
+        ErrorKind::PremiumRequired.recovery(),
+        Recovery::UpgradeToPremium
+    );
+    assert!(ErrorKind::InvalidToken.recoverable());
+    assert_eq!(
+        ErrorKind::MissingScopes.recovery(),
+        Recovery::Reauthenticate
+    );
+    assert_eq!(
+        ErrorKind::MissingScopes.recovery().message(),
+        "Please log in again"
+    );
+}
@@ -0,0 +1,364 @@
+#![cfg(not(target_arch = "wasm32"))]
+//! The payloads in `tests/fixtures` have the shapes the SDK sends, with the fields the model doesn't read,
+//! every one of them has to keep deserializing.
+//! A fixture that stops parsing, or loses a field the model has when it's serialized again, means the model broke backward compatibility.
+
+use std::{fs, path::PathBuf};
+
+use rust_spotify_web_playback_sdk::{
+    restrictions::{Action, Capabilities},
+    structs::{is_track, state_change::StateChange, web_playback::State, RepeatMode, Track},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+fn fixtures(kind: &str) -> Vec<PathBuf> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(kind);
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("can't read {}: {e}", dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {}", dir.display());
+    paths
+}
+
+fn raw(path: &PathBuf) -> Value {
+    let text = fs::read_to_string(path).unwrap();
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("{} isn't JSON: {e}", path.display()))
+}
+
+fn parse<T: DeserializeOwned>(path: &PathBuf) -> T {
+    serde_json::from_value(raw(path)).unwrap_or_else(|e| {
+        panic!(
+            "{} doesn't deserialize into {} anymore: {e}",
+            path.display(),
+            std::any::type_name::<T>()
+        )
+    })
+}
+
+fn fixture(kind: &str, name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(kind)
+        .join(format!("{name}.json"))
+}
+
+fn load<T: DeserializeOwned>(kind: &str, name: &str) -> T {
+    parse(&fixture(kind, name))
+}
+
+/// Checks that every field the model serialized is in the payload with the same value, at `path`.
+/// The payload can have more fields, the SDK sends some the model doesn't read.
+/// A null stands for a missing optional field, and every number is compared as a float.
+fn is_covered(serialized: &Value, payload: &Value, path: &str) -> Result<(), String> {
+    match (serialized, payload) {
+        (Value::Null, Value::Null) => Ok(()),
+        (Value::Object(serialized), Value::Object(payload)) => {
+            for (key, value) in serialized {
+                let path = format!("{path}.{key}");
+                match payload.get(key) {
+                    Some(payload) => is_covered(value, payload, &path)?,
+                    None if value.is_null() => {}
+                    None => return Err(format!("{path} isn't in the payload")),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(serialized), Value::Array(payload)) if serialized.len() == payload.len() => {
+            serialized
+                .iter()
+                .zip(payload)
+                .enumerate()
+                .try_for_each(|(i, (value, payload))| {
+                    is_covered(value, payload, &format!("{path}[{i}]"))
+                })
+        }
+        (Value::Number(serialized), Value::Number(payload))
+            if serialized.as_f64() == payload.as_f64() =>
+        {
+            Ok(())
+        }
+        (serialized, payload) if serialized == payload => Ok(()),
+        (serialized, payload) => Err(format!("{path} is {serialized} instead of {payload}")),
+    }
+}
+
+/// The paths of the fields in `payload` that the model dropped
+fn unknown_fields(serialized: &Value, payload: &Value, path: &str, unknown: &mut Vec<String>) {
+    match (serialized, payload) {
+        (Value::Object(serialized), Value::Object(payload)) => {
+            for (key, value) in payload {
+                let path = format!("{path}.{key}");
+                match serialized.get(key) {
+                    Some(serialized) => unknown_fields(serialized, value, &path, unknown),
+                    None => unknown.push(path),
+                }
+            }
+        }
+        (Value::Array(serialized), Value::Array(payload)) => {
+            for (i, (serialized, payload)) in serialized.iter().zip(payload).enumerate() {
+                unknown_fields(serialized, payload, &format!("{path}[{i}]"), unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Deserializes every fixture of `kind`, then checks that serializing it again gives back every field the model has
+fn conforms<T>(kind: &str)
+where
+    T: DeserializeOwned + Serialize + PartialEq + std::fmt::Debug,
+{
+    for path in fixtures(kind) {
+        let parsed: T = parse(&path);
+        let serialized = serde_json::to_value(&parsed).unwrap();
+        if let Err(error) = is_covered(&serialized, &raw(&path), "") {
+            panic!(
+                "{} changes when it's serialized again: {error}",
+                path.display()
+            );
+        }
+        assert_eq!(
+            serde_json::from_value::<T>(serialized).unwrap(),
+            parsed,
+            "{} doesn't round trip",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn every_state_change_fixture_conforms() {
+    conforms::<StateChange>("state_change");
+}
+
+#[test]
+fn every_state_fixture_conforms() {
+    conforms::<State>("state");
+}
+
+#[test]
+fn every_track_fixture_conforms() {
+    conforms::<Track>("track");
+}
+
+#[test]
+fn the_fields_the_model_drops_are_kept() {
+    let state: StateChange = load("state_change", "track");
+    let mut unknown = Vec::new();
+    unknown_fields(
+        &serde_json::to_value(&state).unwrap(),
+        &raw(&fixture("state_change", "track")),
+        "",
+        &mut unknown,
+    );
+    assert!(unknown.contains(&".track_window.current_track.type".to_string()));
+    assert!(unknown.contains(&".context.metadata.name".to_string()));
+}
+
+#[test]
+fn the_current_tracks_are_tracks_too() {
+    for path in fixtures("state_change") {
+        let state: StateChange = parse(&path);
+        let track = serde_json::to_value(&state.track_window.current_track).unwrap();
+        assert!(serde_json::from_value::<Track>(track).is_ok());
+    }
+}
+
+#[test]
+fn a_playing_track() {
+    let state: StateChange = load("state_change", "track");
+    assert!(!state.paused);
+    assert_eq!(state.position, 73214);
+    assert_eq!(state.duration, 320357);
+    assert_eq!(
+        state.context.uri.as_deref(),
+        Some("spotify:playlist:37i9dQZEVXcJZyENOWUFo7")
+    );
+    assert_eq!(
+        state
+            .context
+            .metadata
+            .as_ref()
+            .unwrap()
+            .context_description
+            .as_deref(),
+        Some("Discover Weekly")
+    );
+    assert_eq!(RepeatMode::from_sdk(state.repeat_mode), RepeatMode::Context);
+    assert!(state.shuffle);
+    assert_eq!(state.shuffle_mode, Some(1));
+    assert_eq!(
+        state.playback_features.signal_ids,
+        Some(vec!["gapless".into()])
+    );
+    assert_eq!(state.track_window.current_track.name, "One More Time");
+    assert_eq!(state.track_window.next_tracks.len(), 2);
+    assert!(is_track(
+        &state.track_window.previous_tracks[0],
+        "spotify:track:3nsfB1vus2qaloUdcBZvDu"
+    ));
+
+    let capabilities = Capabilities::from_state(&state);
+    assert!(!capabilities.can_resume());
+    for action in Action::ALL
+        .into_iter()
+        .filter(|action| *action != Action::Resuming)
+    {
+        assert!(capabilities.allows(action), "{action:?} should be allowed");
+    }
+
+    let current: State = load("state", "track");
+    assert_eq!(current.position, state.position);
+    assert_eq!(
+        current.track_window.current_track,
+        state.track_window.current_track
+    );
+}
+
+#[test]
+fn a_podcast_at_one_and_a_half_speed() {
+    let state: StateChange = load("state_change", "episode_speed");
+    assert_eq!(state.playback_speed, 1.5);
+    assert_eq!(state.playback_features.playback_speed.current, 1.5);
+    assert_eq!(state.playback_features.playback_speed.selected, 1.5);
+    assert!(!state.playback_features.playback_speed.restricted);
+    let episode = &state.track_window.current_track;
+    assert_eq!(episode.track_type, "episode");
+    assert!(episode.uri.starts_with("spotify:episode:"));
+    assert!(episode.album.uri.starts_with("spotify:show:"));
+    assert_eq!(episode.metadata, None);
+
+    let capabilities = Capabilities::from_state(&state);
+    assert_eq!(
+        capabilities.reasons(Action::TogglingShuffle),
+        Some(&["episode".to_string()][..])
+    );
+    assert!(!capabilities.can_set_repeat(RepeatMode::Off, RepeatMode::Context));
+    assert!(capabilities.can_seek());
+
+    let current: State = load("state", "episode_speed");
+    assert_eq!(current.disallows.skipping_next, Some(true));
+}
+
+#[test]
+fn an_ad() {
+    let state: StateChange = load("state_change", "ad");
+    assert_eq!(state.context.uri, None);
+    assert_eq!(state.playback_id, "");
+    assert!(state.playback_features.playback_speed.restricted);
+    let ad = &state.track_window.current_track;
+    assert_eq!(ad.track_type, "ad");
+    assert!(ad.uri.starts_with("spotify:ad:"));
+    assert_eq!(ad.id, "");
+    // its only image has no size
+    assert_eq!(ad.album.image(300), ad.album.images.first());
+
+    let capabilities = Capabilities::from_state(&state);
+    for action in [Action::Seeking, Action::SkippingNext, Action::SkippingPrev] {
+        assert_eq!(capabilities.reasons(action), Some(&["ad".to_string()][..]));
+    }
+    assert!(capabilities.can_pause());
+
+    let current: State = load("state", "ad");
+    assert_eq!(current.disallows.seeking, Some(true));
+    assert_eq!(current.context.uri, None);
+}
+
+#[test]
+fn a_local_file() {
+    let state: StateChange = load("state_change", "local_file");
+    let local = &state.track_window.current_track;
+    assert!(local.uri.starts_with("spotify:local:"));
+    assert_eq!(local.id, "");
+    assert_eq!(local.linked_from, None);
+    assert_eq!(local.album.image(300), None);
+    assert_eq!(local.artists[0].uri, "");
+
+    let track: Track = load("track", "local_file");
+    assert_eq!(&track, local);
+}
+
+#[test]
+fn a_restricted_state() {
+    let state: StateChange = load("state_change", "restricted");
+    assert!(state.paused);
+    assert_eq!(state.position, state.duration);
+    assert_eq!(state.shuffle_mode, None);
+    assert_eq!(RepeatMode::from_sdk(state.repeat_mode), RepeatMode::Track);
+    assert_eq!(state.disallows.undefined, Some(true));
+    assert_eq!(
+        state.restrictions.undefined,
+        Some(vec!["unknown_reason".into()])
+    );
+    assert_eq!(
+        state.context.metadata.as_ref().unwrap().context_description,
+        None
+    );
+
+    let capabilities = Capabilities::from_state(&state);
+    assert!(capabilities.can_resume());
+    assert!(!capabilities.can_pause());
+    assert!(!capabilities.can_skip_next());
+    assert!(capabilities.can_skip_prev());
+    assert_eq!(
+        capabilities.reasons(Action::TogglingShuffle),
+        Some(&["disallowed_by_context".to_string()][..])
+    );
+    // disallowed without a reason
+    assert_eq!(
+        capabilities.reasons(Action::TogglingRepeatTrack),
+        Some(&[][..])
+    );
+}
+
+#[test]
+fn a_null_context() {
+    let state: StateChange = load("state_change", "null_context");
+    assert_eq!(state.context.uri, None);
+    assert_eq!(state.context.metadata, None);
+    assert!(state.loading);
+    assert_eq!(state.playback_quality, "UNKNOWN");
+    assert!(state.track_window.next_tracks.is_empty());
+
+    let current: State = load("state", "null_context");
+    assert_eq!(current.context.uri, None);
+    assert!(current.paused);
+}
+
+#[test]
+fn standalone_tracks() {
+    let song: Track = load("track", "song");
+    assert_eq!(song.duration_ms, 320357);
+    assert_eq!(song.artists[0].name, "Daft Punk");
+    assert_eq!(
+        song.album.image(300).and_then(|image| image.width),
+        Some(300)
+    );
+    assert_eq!(
+        song.linked_from
+            .as_ref()
+            .and_then(|linked| linked.uri.as_deref()),
+        None
+    );
+
+    let relinked: Track = load("track", "relinked");
+    assert!(is_track(&relinked, "spotify:track:3nsfB1vus2qaloUdcBZvDu"));
+    assert!(is_track(&relinked, &relinked.uri));
+
+    let episode: Track = load("track", "episode");
+    assert_eq!(episode.track_type, "episode");
+    assert_eq!(episode.artists[0].url, None);
+
+    let ad: Track = load("track", "ad");
+    assert_eq!(ad.track_type, "ad");
+    assert_eq!(ad.duration_ms, 30000);
+}
@@ -0,0 +1,88 @@
+//! The JavaScript API against a fake `Spotify.Player` in Node,
+//! run it with `cargo test --target wasm32-unknown-unknown --features js-api --test js_api`.
+#![cfg(all(target_arch = "wasm32", feature = "js-api"))]
+
+use rust_spotify_web_playback_sdk::{js_api::RustSpotifyPlayer, prelude as sp};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "/tests/support/fake_spotify.js")]
+extern "C" {
+    fn install();
+    fn tick() -> js_sys::Promise;
+    fn emit(event: &str, payload: &JsValue) -> u32;
+}
+
+/// The message the promise rejects with
+async fn rejection(promise: js_sys::Promise) -> String {
+    let error = JsFuture::from(promise).await.unwrap_err();
+    String::from(error.unchecked_into::<js_sys::Error>().message())
+}
+
+fn calls() -> f64 {
+    js_sys::Reflect::get(&js_sys::global(), &"subscriberCalls".into())
+        .unwrap()
+        .as_f64()
+        .unwrap_or_default()
+}
+
+#[wasm_bindgen_test]
+async fn subscribe_returns_the_unsubscribe_function() {
+    install();
+    let player = RustSpotifyPlayer::new(
+        "js player".into(),
+        js_sys::Function::new_no_args("return 'fake token'"),
+        None,
+    );
+    while !sp::player_ready() {
+        JsFuture::from(tick()).await.unwrap();
+    }
+    let callback = js_sys::Function::new_no_args(
+        "globalThis.subscriberCalls = (globalThis.subscriberCalls || 0) + 1",
+    );
+
+    let unsubscribe: js_sys::Function = player.subscribe(callback.clone()).unchecked_into();
+    emit("player_state_changed", &JsValue::NULL);
+    assert_eq!(calls(), 1.0);
+    unsubscribe.call0(&JsValue::NULL).unwrap();
+    // calling it again does nothing
+    unsubscribe.call0(&JsValue::NULL).unwrap();
+    emit("player_state_changed", &JsValue::NULL);
+    assert_eq!(calls(), 1.0);
+
+    // freeing the player removes the callbacks that are left
+    player.subscribe(callback);
+    emit("player_state_changed", &JsValue::NULL);
+    assert_eq!(calls(), 2.0);
+    drop(player);
+    emit("player_state_changed", &JsValue::NULL);
+    assert_eq!(calls(), 2.0);
+}
+
+#[wasm_bindgen_test]
+async fn enqueue_rejects_invalid_commands() {
+    install();
+    let player = RustSpotifyPlayer::new(
+        "js player".into(),
+        js_sys::Function::new_no_args("return 'fake token'"),
+        None,
+    );
+    assert_eq!(
+        rejection(player.enqueue("rewind".into(), None)).await,
+        "invalid request: unknown command rewind"
+    );
+    assert_eq!(
+        rejection(player.enqueue("seek".into(), None)).await,
+        "invalid request: seek needs a value"
+    );
+    assert_eq!(
+        rejection(player.enqueue("setVolume".into(), Some(f64::NAN))).await,
+        "invalid request: setVolume needs a finite value"
+    );
+    assert_eq!(
+        rejection(player.enqueue("setVolume".into(), Some(1.5))).await,
+        "invalid request: setVolume needs a volume between 0 and 1"
+    );
+    assert_eq!(player.queue_length(), 0);
+}
@@ -0,0 +1,72 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use rust_spotify_web_playback_sdk::{
+    command_queue::{Command, CommandQueue},
+    prelude as sp,
+    store::{player_store, Lifecycle},
+};
+
+#[test]
+fn init_does_nothing() {
+    sp::init(|| "token".into(), || {}, "native player", 0.5, false);
+    assert!(!sp::player_ready());
+    assert_eq!(player_store().get().lifecycle, Lifecycle::Uninitialized);
+}
+
+#[tokio::test]
+async fn commands_are_not_in_browser() {
+    assert_eq!(sp::pause().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::resume().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::toggle_play().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::seek(1000).await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::next_track().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::previous_track().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(
+        sp::pause_confirmed(sp::STATE_TIMEOUT_MS).await,
+        Err(sp::SdkError::NotInBrowser)
+    );
+    assert_eq!(
+        sp::seek_confirmed(1000, sp::STATE_TIMEOUT_MS).await,
+        Err(sp::SdkError::NotInBrowser)
+    );
+    assert_eq!(sp::connect().await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(sp::set_volume(0.5).await, Err(sp::SdkError::NotInBrowser));
+    assert_eq!(
+        sp::get_current_state().await,
+        Err(sp::SdkError::NotInBrowser)
+    );
+    assert_eq!(sp::disconnect(), Err(sp::SdkError::NotInBrowser));
+}
+
+#[tokio::test]
+async fn store_and_queue_are_not_in_browser() {
+    assert_eq!(
+        player_store().send(Command::Pause).await,
+        Err(sp::SdkError::NotInBrowser)
+    );
+    assert_eq!(player_store().get().volume, None);
+    let queue = CommandQueue::new();
+    assert_eq!(
+        queue.send(Command::Seek(1000)).await,
+        Err(sp::SdkError::NotInBrowser)
+    );
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn supervisors_need_a_browser() {
+    assert!(matches!(
+        rust_spotify_web_playback_sdk::reconnect::start(Default::default()),
+        Err(sp::SdkError::NotInBrowser)
+    ));
+    assert!(matches!(
+        rust_spotify_web_playback_sdk::watchdog::start(Default::default()),
+        Err(sp::SdkError::NotInBrowser)
+    ));
+}
+
+#[test]
+fn add_listener_fails_without_a_player() {
+    let result = sp::add_listener!("player_state_changed", |_: sp::StateChange| {});
+    assert!(result.is_err());
+}
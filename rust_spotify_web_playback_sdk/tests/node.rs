@@ -0,0 +1,663 @@
+//! The crate against a fake `Spotify.Player` in Node, run it with `cargo test --target wasm32-unknown-unknown --test node`.
+//! Every test shares the one player `init` creates, like a page does, so each one starts with [`setup`].
+#![cfg(target_arch = "wasm32")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rust_spotify_web_playback_sdk::{
+    backend::{self, SdkEvent},
+    command_queue::{Command, CommandQueue},
+    events::{add_event_listener, remove_event_listener, Event, ListenerId},
+    js_wrapper, prelude as sp,
+    reconnect::{self, ReconnectOptions, ReconnectReason},
+    store::{player_store, Lifecycle},
+    token::{AccessToken, TokenCache},
+};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "/tests/support/fake_spotify.js")]
+extern "C" {
+    fn install();
+    fn reset();
+    fn emit(event: &str, payload: &JsValue) -> u32;
+    #[wasm_bindgen(js_name = takeCalls)]
+    fn take_calls() -> Vec<String>;
+    #[wasm_bindgen(js_name = setConnectResult)]
+    fn set_connect_result(result: bool);
+    #[wasm_bindgen(js_name = failNext)]
+    fn fail_next(method: &str, message: &str);
+    #[wasm_bindgen(js_name = emitAfter)]
+    fn emit_after(method: &str, event: &str, payload: &JsValue);
+    #[wasm_bindgen(js_name = holdNext)]
+    fn hold_next(method: &str);
+    fn release(method: &str);
+    #[wasm_bindgen(js_name = setOnline)]
+    fn set_online(online: bool);
+    #[wasm_bindgen(js_name = setVisible)]
+    fn set_visible(visible: bool);
+    #[wasm_bindgen(js_name = setCurrentState)]
+    fn set_current_state(state: &JsValue);
+    #[wasm_bindgen(js_name = playerName)]
+    fn player_name() -> Option<String>;
+    #[wasm_bindgen(js_name = requestToken)]
+    fn request_token() -> js_sys::Promise;
+    fn tick() -> js_sys::Promise;
+    #[wasm_bindgen(js_name = stateChange)]
+    fn state_change(track_id: &str, paused: bool, position: u32) -> JsValue;
+    fn state(track_id: &str, paused: bool, position: u32) -> JsValue;
+}
+
+thread_local! {
+    static INITIALIZED: RefCell<bool> = const { RefCell::new(false) };
+}
+
+/// Initializes the crate once, then forgets what the previous test recorded and clears the latest state
+async fn setup() {
+    install();
+    if !INITIALIZED.with(|initialized| initialized.replace(true)) {
+        sp::init(|| "fake token".into(), || {}, "node player", 0.5, false);
+    }
+    while !sp::player_ready() {
+        JsFuture::from(tick()).await.unwrap();
+    }
+    reset();
+    emit("player_state_changed", &JsValue::NULL);
+    take_calls();
+}
+
+fn error(message: &str) -> JsValue {
+    let error = js_sys::Object::new();
+    js_sys::Reflect::set(&error, &"message".into(), &message.into()).unwrap();
+    error.into()
+}
+
+/// Records what the reconnect supervisors emit
+fn record_reconnects() -> (Rc<RefCell<Vec<Event>>>, ListenerId) {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let events = events.clone();
+        move |event: &Event| {
+            if matches!(
+                event,
+                Event::ReconnectAttempt { .. }
+                    | Event::Reconnected { .. }
+                    | Event::ReconnectGaveUp { .. }
+            ) {
+                events.borrow_mut().push(event.clone())
+            }
+        }
+    });
+    (events, listener)
+}
+
+fn attempt(attempt: u32, delay_ms: u32, reason: ReconnectReason) -> Event {
+    Event::ReconnectAttempt {
+        attempt,
+        delay_ms,
+        reason,
+    }
+}
+
+/// Lets the timers of the page run until `done`
+async fn wait_until(done: impl Fn() -> bool) {
+    for _ in 0..500 {
+        if done() {
+            return;
+        }
+        JsFuture::from(tick()).await.unwrap();
+    }
+    panic!("gave up waiting");
+}
+
+fn device(device_id: &str) -> JsValue {
+    let player = js_sys::Object::new();
+    js_sys::Reflect::set(&player, &"device_id".into(), &device_id.into()).unwrap();
+    player.into()
+}
+
+#[wasm_bindgen_test]
+async fn init_creates_the_player() {
+    setup().await;
+    assert!(sp::player_ready());
+    assert_eq!(player_name().as_deref(), Some("node player"));
+    let token = JsFuture::from(request_token()).await.unwrap();
+    assert_eq!(token.as_string().as_deref(), Some("fake token"));
+    assert_ne!(player_store().get().lifecycle, Lifecycle::Uninitialized);
+}
+
+#[wasm_bindgen_test]
+async fn connect_resolves_with_the_connect_result() {
+    setup().await;
+    assert_eq!(sp::connect().await, Ok(()));
+    set_connect_result(false);
+    assert_eq!(
+        sp::connect().await,
+        Err(sp::SdkError::Command("could not connect".into()))
+    );
+    fail_next("connect", "network down");
+    assert!(matches!(
+        sp::connect().await,
+        Err(sp::SdkError::Command(message)) if message.contains("network down")
+    ));
+    assert_eq!(take_calls(), ["connect", "connect", "connect"]);
+}
+
+#[wasm_bindgen_test]
+async fn every_command_calls_the_player() {
+    setup().await;
+    sp::pause().await.unwrap();
+    sp::resume().await.unwrap();
+    sp::toggle_play().await.unwrap();
+    sp::seek(1234).await.unwrap();
+    sp::previous_track().await.unwrap();
+    sp::next_track().await.unwrap();
+    sp::set_volume(0.25).await.unwrap();
+    assert_eq!(sp::get_volume().await, Ok(0.25));
+    sp::set_name("renamed".into()).await.unwrap();
+    assert_eq!(player_name().as_deref(), Some("renamed"));
+    sp::activate_element().await.unwrap();
+    assert_eq!(sp::get_current_state().await, Ok(None));
+    set_current_state(&state("1", true, 500));
+    let current = sp::get_current_state().await.unwrap().unwrap();
+    assert_eq!(current.position, 500);
+    assert_eq!(current.track_window.current_track.uri, "spotify:track:1");
+    sp::disconnect().unwrap();
+    assert_eq!(
+        take_calls(),
+        [
+            "pause",
+            "resume",
+            "togglePlay",
+            "seek(1234)",
+            "previousTrack",
+            "nextTrack",
+            "setVolume(0.25)",
+            "getVolume",
+            "setName(renamed)",
+            "activateElement",
+            "getCurrentState",
+            "getCurrentState",
+            "disconnect",
+        ]
+    );
+}
+
+#[wasm_bindgen_test]
+async fn rejected_commands_fail() {
+    setup().await;
+    fail_next("pause", "no list was loaded");
+    let Err(sp::SdkError::Command(message)) = sp::pause().await else {
+        panic!("the pause should fail");
+    };
+    assert!(message.contains("no list was loaded"));
+    fail_next("setVolume", "volume out of range");
+    assert!(sp::set_volume(2.0).await.is_err());
+}
+
+#[wasm_bindgen_test]
+async fn commands_respect_the_latest_state() {
+    setup().await;
+    emit("player_state_changed", &state_change("1", true, 0));
+    assert!(matches!(
+        sp::pause().await,
+        Err(sp::SdkError::Disallowed { .. })
+    ));
+    assert!(take_calls().is_empty());
+    assert!(!sp::capabilities().can_pause());
+}
+
+#[wasm_bindgen_test]
+async fn confirmed_commands_wait_for_the_state_change() {
+    setup().await;
+    emit("player_state_changed", &state_change("1", false, 1000));
+    emit_after(
+        "pause",
+        "player_state_changed",
+        &state_change("1", true, 1000),
+    );
+    let paused = sp::pause_confirmed(1000).await.unwrap();
+    assert!(paused.paused);
+
+    emit_after(
+        "seek",
+        "player_state_changed",
+        &state_change("1", true, 60_000),
+    );
+    let seeked = sp::seek_confirmed(60_000, 1000).await.unwrap();
+    assert_eq!(seeked.position, 60_000);
+
+    emit_after(
+        "nextTrack",
+        "player_state_changed",
+        &state_change("2", true, 0),
+    );
+    let next = sp::next_track_confirmed(1000).await.unwrap();
+    assert_eq!(next.track_window.current_track.uri, "spotify:track:2");
+
+    // nothing confirms this one
+    assert_eq!(sp::resume_confirmed(50).await, Err(sp::SdkError::Timeout));
+}
+
+#[wasm_bindgen_test]
+async fn add_listener_works_for_every_event() {
+    setup().await;
+    let received = Rc::new(RefCell::new(Vec::<String>::new()));
+    let push =
+        |received: &Rc<RefCell<Vec<String>>>, value: String| received.borrow_mut().push(value);
+
+    let r = received.clone();
+    sp::add_listener!("ready", move |player: sp::Player| {
+        push(&r, player.device_id)
+    })
+    .unwrap();
+    let r = received.clone();
+    sp::add_listener!("not_ready", move |player: sp::Player| {
+        push(&r, player.device_id)
+    })
+    .unwrap();
+    let r = received.clone();
+    sp::add_listener!("player_state_changed", move |state: sp::StateChange| {
+        push(&r, state.track_window.current_track.uri)
+    })
+    .unwrap();
+    for event in [
+        "initialization_error",
+        "authentication_error",
+        "account_error",
+        "playback_error",
+    ] {
+        let r = received.clone();
+        let callback = move |error: sp::Error| push(&r, error.message);
+        let added = match event {
+            "initialization_error" => sp::add_listener!("initialization_error", callback),
+            "authentication_error" => sp::add_listener!("authentication_error", callback),
+            "account_error" => sp::add_listener!("account_error", callback),
+            _ => sp::add_listener!("playback_error", callback),
+        };
+        assert_eq!(added, Ok(true));
+    }
+    // add_listener! can't take the argumentless autoplay_failed callback, the backend listener can
+    let r = received.clone();
+    backend::add_listener("autoplay_failed", move |event| {
+        assert_eq!(event, SdkEvent::AutoplayFailed);
+        push(&r, "autoplay".into())
+    })
+    .unwrap();
+
+    emit("ready", &device("device"));
+    emit("not_ready", &device("device"));
+    emit("player_state_changed", &state_change("3", true, 0));
+    emit("initialization_error", &error("init"));
+    emit("authentication_error", &error("auth"));
+    emit("account_error", &error("premium"));
+    emit("playback_error", &error("playback"));
+    emit("autoplay_failed", &JsValue::UNDEFINED);
+    assert_eq!(
+        *received.borrow(),
+        [
+            "device",
+            "device",
+            "spotify:track:3",
+            "init",
+            "auth",
+            "premium",
+            "playback",
+            "autoplay",
+        ]
+    );
+
+    // the crate's own listeners got them too
+    assert_eq!(
+        player_store().get().lifecycle,
+        Lifecycle::Failed(sp::ErrorKind::PremiumRequired)
+    );
+    emit("ready", &device("device"));
+    assert_eq!(player_store().get().lifecycle, Lifecycle::Ready);
+
+    // the player is shared, the next tests shouldn't call these
+    for event in [
+        "ready",
+        "not_ready",
+        "player_state_changed",
+        "initialization_error",
+        "authentication_error",
+        "account_error",
+        "playback_error",
+        "autoplay_failed",
+    ] {
+        assert_eq!(sp::remove_listener(event), Ok(()));
+    }
+    received.borrow_mut().clear();
+    emit("ready", &device("device"));
+    emit("player_state_changed", &JsValue::NULL);
+    assert!(received.borrow().is_empty());
+}
+
+#[wasm_bindgen_test]
+async fn remove_listener_removes_the_callbacks() {
+    setup().await;
+    let calls = Rc::new(RefCell::new(0));
+    let c = calls.clone();
+    sp::add_listener!("playback_error", move |_: sp::Error| *c.borrow_mut() += 1).unwrap();
+    assert_eq!(emit("playback_error", &error("first")), 1);
+    assert_eq!(sp::remove_listener("playback_error"), Ok(()));
+    assert_eq!(emit("playback_error", &error("second")), 0);
+    assert_eq!(*calls.borrow(), 1);
+    assert!(sp::remove_listener("playback_error").is_err());
+    assert_eq!(
+        sp::remove_listener("not_an_event"),
+        Err(sp::SdkError::InvalidRequest("event does not exist".into()))
+    );
+
+    let closure = Closure::new(|_: JsValue| {});
+    assert!(js_wrapper::addListener("autoplay_failed".into(), &closure));
+    assert_eq!(
+        sp::remove_specific_listener("autoplay_failed", &closure),
+        Ok(true)
+    );
+    assert_eq!(
+        sp::remove_specific_listener("autoplay_failed", &closure),
+        Ok(false)
+    );
+}
+
+#[wasm_bindgen_test]
+async fn the_command_queue_coalesces_while_a_command_runs() {
+    setup().await;
+    let queue = CommandQueue::new();
+    let (first, superseded, last, pause) = futures::join!(
+        queue.send(Command::Seek(1000)),
+        queue.send(Command::Seek(2000)),
+        queue.send(Command::Seek(3000)),
+        queue.send(Command::Pause),
+    );
+    assert_eq!(first, Ok(()));
+    assert_eq!(superseded, Err(sp::SdkError::Cancelled));
+    assert_eq!(last, Ok(()));
+    assert_eq!(pause, Ok(()));
+    assert_eq!(take_calls(), ["seek(1000)", "seek(3000)", "pause"]);
+    assert!(queue.is_empty());
+}
+
+#[wasm_bindgen_test]
+async fn a_stopped_supervisor_ignores_the_player() {
+    setup().await;
+    let (events, listener) = record_reconnects();
+    let supervisor = reconnect::start(ReconnectOptions {
+        initial_delay_ms: 1,
+        max_delay_ms: 1,
+        ..Default::default()
+    })
+    .unwrap();
+
+    emit("not_ready", &device("device"));
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    assert_eq!(
+        *events.borrow(),
+        [
+            attempt(1, 1, ReconnectReason::NotReady),
+            Event::Reconnected { attempts: 1 },
+        ]
+    );
+    assert_eq!(take_calls(), ["connect"]);
+
+    // a running supervisor would reconnect again
+    supervisor.stop();
+    emit("not_ready", &device("device"));
+    set_online(false);
+    set_online(true);
+    for _ in 0..10 {
+        JsFuture::from(tick()).await.unwrap();
+    }
+    assert_eq!(events.borrow().len(), 2);
+    assert!(take_calls().is_empty());
+
+    emit("ready", &device("device"));
+    assert!(remove_event_listener(listener));
+}
+
+#[wasm_bindgen_test]
+async fn reconnecting_backs_off_and_gives_up() {
+    setup().await;
+    let (events, listener) = record_reconnects();
+    set_connect_result(false);
+    let supervisor = reconnect::start(ReconnectOptions {
+        max_attempts: 3,
+        initial_delay_ms: 10,
+        max_delay_ms: 25,
+        ..Default::default()
+    })
+    .unwrap();
+
+    emit("not_ready", &device("device"));
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    let events = events.borrow();
+    let delays: Vec<u32> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::ReconnectAttempt { delay_ms, .. } => Some(*delay_ms),
+            _ => None,
+        })
+        .collect();
+    // it doubles with up to 50% jitter, and the jitter doesn't push it over the maximum
+    assert!((10..=15).contains(&delays[0]), "{delays:?}");
+    assert!((20..=25).contains(&delays[1]), "{delays:?}");
+    assert_eq!(delays[2..], [25]);
+    assert_eq!(events.last(), Some(&Event::ReconnectGaveUp { attempts: 3 }));
+    assert_eq!(take_calls(), ["connect", "connect", "connect"]);
+
+    supervisor.stop();
+    emit("ready", &device("device"));
+    assert!(remove_event_listener(listener));
+}
+
+#[wasm_bindgen_test]
+async fn reconnecting_tries_again_after_a_failed_connect() {
+    setup().await;
+    let (events, listener) = record_reconnects();
+    fail_next("connect", "network error");
+    let supervisor = reconnect::start(ReconnectOptions {
+        initial_delay_ms: 1,
+        max_delay_ms: 1,
+        ..Default::default()
+    })
+    .unwrap();
+
+    emit("not_ready", &device("device"));
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    assert_eq!(
+        *events.borrow(),
+        [
+            attempt(1, 1, ReconnectReason::NotReady),
+            attempt(2, 1, ReconnectReason::NotReady),
+            Event::Reconnected { attempts: 2 },
+        ]
+    );
+
+    supervisor.stop();
+    emit("ready", &device("device"));
+    assert!(remove_event_listener(listener));
+}
+
+#[wasm_bindgen_test]
+async fn an_authentication_error_refreshes_the_token_before_connecting() {
+    setup().await;
+    let (events, listener) = record_reconnects();
+    let calls_before_refresh = Rc::new(RefCell::new(None));
+    let cache = TokenCache::new({
+        let calls_before_refresh = calls_before_refresh.clone();
+        move || {
+            *calls_before_refresh.borrow_mut() = Some(take_calls());
+            async {
+                Ok(AccessToken {
+                    access_token: "fresh token".into(),
+                    expires_in: 3600,
+                })
+            }
+        }
+    });
+    let supervisor = reconnect::start(ReconnectOptions {
+        initial_delay_ms: 1,
+        max_delay_ms: 1,
+        token_cache: Some(cache.clone()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    emit("authentication_error", &error("Invalid token scopes."));
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    assert_eq!(
+        *events.borrow(),
+        [
+            attempt(1, 1, ReconnectReason::AuthenticationError),
+            Event::Reconnected { attempts: 1 },
+        ]
+    );
+    assert_eq!(calls_before_refresh.take(), Some(Vec::new()));
+    assert_eq!(take_calls(), ["connect"]);
+    assert_eq!(cache.token().as_deref(), Some("fresh token"));
+
+    supervisor.stop();
+    emit("ready", &device("device"));
+    assert!(remove_event_listener(listener));
+}
+
+#[wasm_bindgen_test]
+async fn coming_back_online_only_reconnects_a_player_that_was_ready() {
+    setup().await;
+    let (events, listener) = record_reconnects();
+    // like a first `connect` that didn't get to `ready`
+    emit("not_ready", &device("device"));
+    emit(
+        "initialization_error",
+        &error("Failed to initialize player"),
+    );
+    let supervisor = reconnect::start(ReconnectOptions {
+        initial_delay_ms: 1,
+        max_delay_ms: 1,
+        ..Default::default()
+    })
+    .unwrap();
+
+    set_online(false);
+    set_online(true);
+    set_visible(false);
+    set_visible(true);
+    JsFuture::from(tick()).await.unwrap();
+    assert!(events.borrow().is_empty());
+    assert!(take_calls().is_empty());
+
+    // the player drops off while the browser is offline, and the attempt waits for it to come back
+    emit("ready", &device("device"));
+    set_online(false);
+    emit("not_ready", &device("device"));
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    assert!(take_calls().is_empty());
+    set_online(true);
+    wait_until(|| !supervisor.is_reconnecting()).await;
+    assert_eq!(
+        *events.borrow(),
+        [
+            attempt(1, 1, ReconnectReason::NotReady),
+            attempt(1, 1, ReconnectReason::Online),
+            Event::Reconnected { attempts: 1 },
+        ]
+    );
+    assert_eq!(take_calls(), ["connect"]);
+
+    supervisor.stop();
+    emit("ready", &device("device"));
+    assert!(remove_event_listener(listener));
+}
+
+#[wasm_bindgen_test]
+async fn malformed_payloads_are_dropped() {
+    setup().await;
+    let received = Rc::new(RefCell::new(Vec::new()));
+    for event in ["ready", "playback_error"] {
+        let r = received.clone();
+        backend::add_listener(event, move |event| r.borrow_mut().push(event)).unwrap();
+    }
+
+    let lifecycle = player_store().get().lifecycle;
+    emit("ready", &JsValue::from(5));
+    emit("playback_error", &JsValue::from("not an error"));
+    assert!(received.borrow().is_empty());
+    assert_eq!(player_store().get().lifecycle, lifecycle);
+
+    emit("playback_error", &error("playback"));
+    assert_eq!(received.borrow().len(), 1);
+    for event in ["ready", "playback_error"] {
+        assert_eq!(sp::remove_listener(event), Ok(()));
+    }
+}
+
+#[wasm_bindgen_test]
+async fn a_rejected_volume_falls_back_to_the_one_sent_after_it() {
+    setup().await;
+    let store = player_store();
+    store.send(Command::SetVolume(0.5)).await.unwrap();
+    fail_next("setVolume", "rejected");
+    let (rejected, accepted) = futures::join!(
+        store.send(Command::SetVolume(0.2)),
+        store.send(Command::SetVolume(0.8)),
+    );
+    assert!(rejected.is_err());
+    assert_eq!(accepted, Ok(()));
+    assert_eq!(store.get().volume, Some(0.8));
+    assert_eq!(sp::get_volume().await, Ok(0.8));
+}
+
+#[wasm_bindgen_test]
+async fn an_intermediate_state_leaves_running_commands_pending() {
+    setup().await;
+    let store = player_store();
+    emit("player_state_changed", &state_change("1", false, 1000));
+    let rollbacks = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let rollbacks = rollbacks.clone();
+        move |event: &Event| {
+            if let Event::OptimisticRollback { command, .. } = event {
+                rollbacks.borrow_mut().push(*command)
+            }
+        }
+    });
+
+    hold_next("seek");
+    hold_next("pause");
+    let (seek, pause, ()) = futures::join!(
+        store.send(Command::Seek(60_000)),
+        store.send(Command::Pause),
+        async {
+            JsFuture::from(tick()).await.unwrap();
+            // the SDK reports the playback before either command landed
+            emit("player_state_changed", &state_change("1", false, 1500));
+            assert!(store.is_pending());
+            assert_eq!(store.get().position(), Some(60_000));
+            assert_eq!(store.get().paused(), Some(true));
+            release("seek");
+            release("pause");
+            JsFuture::from(tick()).await.unwrap();
+            emit("player_state_changed", &state_change("1", true, 60_000));
+        },
+    );
+    assert_eq!((seek, pause), (Ok(()), Ok(())));
+    assert!(rollbacks.borrow().is_empty());
+    assert!(!store.is_pending());
+
+    // a command that fails after a state arrived is still rolled back
+    hold_next("resume");
+    fail_next("resume", "rejected");
+    let (resume, ()) = futures::join!(store.send(Command::Resume), async {
+        JsFuture::from(tick()).await.unwrap();
+        emit("player_state_changed", &state_change("1", false, 60_000));
+        emit("player_state_changed", &state_change("1", true, 60_000));
+        release("resume");
+    });
+    assert!(matches!(resume, Err(sp::SdkError::Command(_))));
+    assert_eq!(store.get().paused(), Some(true));
+    assert_eq!(*rollbacks.borrow(), [Command::Resume]);
+    assert!(remove_event_listener(listener));
+}
@@ -0,0 +1,88 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use rust_spotify_web_playback_sdk::{
+    restrictions::{Action, Capabilities},
+    structs::{state_change::StateChange, RepeatMode},
+};
+
+fn ad_state() -> StateChange {
+    serde_json::from_value(serde_json::json!({
+        "context": { "uri": null, "metadata": null },
+        "disallows": {
+            "resuming": true,
+            "seeking": true,
+            "skipping_next": true,
+            "skipping_prev": true,
+            "toggling_repeat_track": true
+        },
+        "duration": 30000,
+        "loading": false,
+        "paused": false,
+        "playback_features": {
+            "hifi_status": "NONE",
+            "playback_speed": { "current": 1, "restricted": true, "selected": 1 },
+            "signal_ids": null
+        },
+        "playback_id": "",
+        "playback_quality": "UNKNOWN",
+        "playback_speed": 1,
+        "position": 1200,
+        "repeat_mode": 0,
+        "restrictions": {
+            "disallow_resuming_reasons": ["not_paused"],
+            "disallow_seeking_reasons": ["ad"],
+            "disallow_skipping_next_reasons": ["ad"],
+            "disallow_skipping_prev_reasons": ["ad"],
+            "disallow_toggling_repeat_track_reasons": []
+        },
+        "shuffle": false,
+        "shuffle_mode": 0,
+        "timestamp": 0,
+        "track_window": {
+            "current_track": {
+                "album": { "uri": "", "name": "", "images": [] },
+                "artists": [],
+                "duration_ms": 30000,
+                "id": "ad",
+                "is_playable": true,
+                "linked_from": null,
+                "media_type": "audio",
+                "metadata": null,
+                "name": "Advertisement",
+                "track_type": "ad",
+                "uid": "ad",
+                "uri": "spotify:ad:1"
+            },
+            "next_tracks": [],
+            "previous_tracks": []
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn capabilities_follow_disallows() {
+    let capabilities = Capabilities::from_state(&ad_state());
+    assert!(capabilities.can_pause());
+    assert!(!capabilities.can_resume());
+    assert!(!capabilities.can_seek());
+    assert!(!capabilities.can_skip_next());
+    assert!(capabilities.can_toggle_shuffle());
+    assert_eq!(
+        capabilities.reasons(Action::SkippingNext),
+        Some(&["ad".to_string()][..])
+    );
+    assert_eq!(capabilities.reasons(Action::Pausing), None);
+
+    assert!(capabilities.can_set_repeat(RepeatMode::Off, RepeatMode::Context));
+    assert!(!capabilities.can_set_repeat(RepeatMode::Context, RepeatMode::Track));
+    assert!(capabilities.can_set_repeat(RepeatMode::Off, RepeatMode::Off));
+}
+
+#[test]
+fn everything_is_allowed_without_a_state() {
+    let capabilities = Capabilities::default();
+    assert!(Action::ALL
+        .into_iter()
+        .all(|action| capabilities.allows(action)));
+}
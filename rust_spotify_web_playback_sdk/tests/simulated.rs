@@ -0,0 +1,327 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rust_spotify_web_playback_sdk::{
+    backend::{self, SdkEvent},
+    command_queue::{Command, CommandQueue},
+    events::{add_event_listener, remove_event_listener, Event},
+    prelude as sp,
+    simulated::{self, SimulatedPlayer, DEVICE_ID},
+    store::{player_store, Lifecycle},
+};
+
+/// Installs a simulated player with three tracks and initializes the crate with it
+fn start(token: &'static str) -> SimulatedPlayer {
+    let player = SimulatedPlayer::new(vec![
+        simulated::track("1", "First", 180_000),
+        simulated::track("2", "Second", 200_000),
+        simulated::track("3", "Third", 120_000),
+    ]);
+    backend::set_backend(player.clone());
+    sp::init(move || token.into(), || {}, "simulated", 0.5, false);
+    player
+}
+
+/// Records the events the player emits
+fn record(events: &[&str]) -> Rc<RefCell<Vec<SdkEvent>>> {
+    let recorded = Rc::new(RefCell::new(Vec::new()));
+    for event in events {
+        let recorded = recorded.clone();
+        assert_eq!(
+            backend::add_listener(event, move |event| recorded.borrow_mut().push(event)),
+            Ok(true)
+        );
+    }
+    recorded
+}
+
+fn current_name(player: &SimulatedPlayer) -> String {
+    player.current_track().unwrap().name
+}
+
+#[tokio::test]
+async fn connect_emits_ready_and_the_first_state() {
+    let player = start("token");
+    assert!(sp::player_ready());
+    assert_eq!(player.name(), "simulated");
+    let events = record(&["ready", "player_state_changed"]);
+    assert_eq!(sp::connect().await, Ok(()));
+
+    let events = events.borrow();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[0], SdkEvent::Ready(ready) if ready.device_id == DEVICE_ID));
+    let SdkEvent::PlayerStateChanged(Some(state)) = &events[1] else {
+        panic!("expected a state, got {:?}", events[1]);
+    };
+    assert!(state.paused);
+    assert_eq!(state.position, 0);
+    assert_eq!(state.track_window.current_track.name, "First");
+    assert_eq!(state.track_window.next_tracks.len(), 2);
+    assert_eq!(state.disallows.pausing, Some(true));
+
+    let snapshot = player_store().get();
+    assert_eq!(snapshot.lifecycle, Lifecycle::Ready);
+    assert_eq!(snapshot.device_id.as_deref(), Some(DEVICE_ID));
+    assert_eq!(snapshot.volume, Some(0.5));
+    assert_eq!(sp::device_id().as_deref(), Some(DEVICE_ID));
+}
+
+#[tokio::test]
+async fn an_empty_token_fails_to_connect() {
+    let player = start("");
+    let events = record(&["authentication_error"]);
+    assert_eq!(
+        sp::connect().await,
+        Err(sp::SdkError::Command("could not connect".into()))
+    );
+    assert!(!player.is_connected());
+    assert_eq!(events.borrow().len(), 1);
+    assert_eq!(
+        player_store().get().lifecycle,
+        Lifecycle::Failed(sp::ErrorKind::InvalidToken)
+    );
+}
+
+#[tokio::test]
+async fn commands_change_the_playback() {
+    let player = start("token");
+    assert_eq!(
+        sp::resume().await,
+        Err(sp::SdkError::Command("the player is not connected".into()))
+    );
+    sp::connect().await.unwrap();
+
+    sp::resume().await.unwrap();
+    assert!(!player.is_paused());
+    player.advance(10_000);
+    assert!((10_000..11_000).contains(&player.position()));
+
+    sp::seek(60_000).await.unwrap();
+    assert!((60_000..61_000).contains(&player.position()));
+    sp::pause().await.unwrap();
+    let paused_at = player.position();
+    player.advance(10_000);
+    assert_eq!(player.position(), paused_at);
+
+    sp::next_track().await.unwrap();
+    assert_eq!(current_name(&player), "Second");
+    assert_eq!(player.position(), 0);
+    sp::previous_track().await.unwrap();
+    assert_eq!(current_name(&player), "First");
+
+    // past the first seconds previous restarts the track
+    sp::next_track().await.unwrap();
+    sp::seek(30_000).await.unwrap();
+    sp::previous_track().await.unwrap();
+    assert_eq!(current_name(&player), "Second");
+    assert_eq!(player.position(), 0);
+
+    sp::toggle_play().await.unwrap();
+    assert!(!player.is_paused());
+
+    sp::set_volume(0.25).await.unwrap();
+    assert_eq!(sp::get_volume().await, Ok(0.25));
+    assert!(sp::set_volume(1.5).await.is_err());
+    sp::set_name("renamed".into()).await.unwrap();
+    assert_eq!(player.name(), "renamed");
+}
+
+#[tokio::test]
+async fn tracks_end_and_the_queue_stops() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+    let events = record(&["player_state_changed"]);
+    sp::resume().await.unwrap();
+
+    player.advance(185_000);
+    assert_eq!(current_name(&player), "Second");
+    assert!((5_000..6_000).contains(&player.position()));
+
+    // the rest of the second track and the whole third one
+    player.advance(195_000 + 120_000);
+    assert_eq!(current_name(&player), "Third");
+    assert!(player.is_paused());
+    assert_eq!(player.position(), 120_000);
+
+    let states: Vec<_> = events
+        .borrow()
+        .iter()
+        .map(|event| match event {
+            SdkEvent::PlayerStateChanged(Some(state)) => {
+                state.track_window.current_track.name.clone()
+            }
+            other => panic!("unexpected event {other:?}"),
+        })
+        .collect();
+    assert_eq!(states, ["First", "Second", "Third", "Third"]);
+
+    // the last track has nothing after it
+    assert!(matches!(
+        sp::next_track().await,
+        Err(sp::SdkError::Disallowed { .. })
+    ));
+    assert!(!sp::capabilities().can_skip_next());
+}
+
+#[tokio::test]
+async fn confirmations_and_the_store_work() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+
+    let state = sp::resume_confirmed(sp::STATE_TIMEOUT_MS).await.unwrap();
+    assert!(!state.paused);
+    let state = sp::seek_confirmed(90_000, sp::STATE_TIMEOUT_MS)
+        .await
+        .unwrap();
+    assert!((90_000..91_000).contains(&(state.position as u32)));
+    let state = sp::next_track_confirmed(sp::STATE_TIMEOUT_MS)
+        .await
+        .unwrap();
+    assert_eq!(state.track_window.current_track.name, "Second");
+
+    assert_eq!(player_store().send(Command::Pause).await, Ok(()));
+    assert_eq!(player_store().get().paused(), Some(true));
+    assert!(player.is_paused());
+    assert!(!player_store().is_pending());
+
+    let state = sp::get_current_state().await.unwrap().unwrap();
+    assert!(state.paused);
+    assert_eq!(state.track_window.previous_tracks.len(), 1);
+}
+
+#[tokio::test]
+async fn scripted_events_reach_the_store() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+
+    let events = record(&["playback_error", "not_ready"]);
+    assert!(player.emit_error("playback_error", "Playback error"));
+    assert!(!player.emit_error("ready", "not an error"));
+    player.transfer_away();
+    assert_eq!(player_store().get().state, None);
+    assert_eq!(player.current_track(), None);
+    player.go_offline();
+    assert_eq!(player_store().get().lifecycle, Lifecycle::NotReady);
+    assert_eq!(sp::device_id(), None);
+    assert_eq!(events.borrow().len(), 2);
+
+    // connecting again brings the playback back
+    sp::connect().await.unwrap();
+    assert_eq!(player_store().get().lifecycle, Lifecycle::Ready);
+    assert_eq!(current_name(&player), "First");
+
+    assert_eq!(sp::remove_listener("not_ready"), Ok(()));
+    player.go_offline();
+    assert_eq!(events.borrow().len(), 2);
+}
+
+#[tokio::test]
+async fn remove_listener_keeps_the_crates_own_listeners() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+    sp::resume().await.unwrap();
+    let events = record(&["player_state_changed", "not_ready"]);
+
+    assert_eq!(sp::remove_listener("player_state_changed"), Ok(()));
+    assert_eq!(sp::remove_listener("not_ready"), Ok(()));
+    let state = sp::pause_confirmed(sp::STATE_TIMEOUT_MS).await.unwrap();
+    assert!(state.paused);
+    assert!(player.is_paused());
+    assert_eq!(player_store().get().paused(), Some(true));
+    assert!(!sp::capabilities().can_pause());
+
+    player.go_offline();
+    assert_eq!(sp::device_id(), None);
+    assert_eq!(player_store().get().lifecycle, Lifecycle::NotReady);
+    // only the user's listeners are gone
+    assert!(events.borrow().is_empty());
+}
+
+#[tokio::test]
+async fn confirmations_time_out() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+
+    // the track is shorter, so the position never gets there
+    let started = std::time::Instant::now();
+    assert_eq!(
+        sp::seek_confirmed(10_000_000, 200).await,
+        Err(sp::SdkError::Timeout)
+    );
+    assert!(started.elapsed() >= std::time::Duration::from_millis(200));
+    assert_eq!(player.position(), 180_000);
+}
+
+#[tokio::test]
+async fn the_command_queue_runs_one_command_at_a_time() {
+    let player = start("token");
+    let queue = CommandQueue::new().with_timeout(1000);
+    sp::connect().await.unwrap();
+
+    let (resume, seek, volume, next) = tokio::join!(
+        queue.send(Command::Resume),
+        queue.send(Command::Seek(30_000)),
+        queue.send(Command::SetVolume(0.25)),
+        queue.send(Command::NextTrack),
+    );
+    assert_eq!(
+        (resume, seek, volume, next),
+        (Ok(()), Ok(()), Ok(()), Ok(()))
+    );
+    assert!(!player.is_paused());
+    assert_eq!(player.volume(), 0.25);
+    assert_eq!(current_name(&player), "Second");
+    assert!(queue.is_empty());
+
+    // a failing command doesn't block the ones after it
+    queue.send(Command::NextTrack).await.unwrap();
+    assert!(matches!(
+        queue.send(Command::NextTrack).await,
+        Err(sp::SdkError::Disallowed { .. })
+    ));
+    assert_eq!(queue.send(Command::Pause).await, Ok(()));
+    assert!(player.is_paused());
+
+    // natively the supervisors need the browser
+    assert_eq!(
+        rust_spotify_web_playback_sdk::watchdog::start(Default::default()).err(),
+        Some(sp::SdkError::NotInBrowser)
+    );
+}
+
+#[tokio::test]
+async fn a_contradicting_state_rolls_back_right_away() {
+    let player = start("token");
+    sp::connect().await.unwrap();
+    let rollbacks = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let rollbacks = rollbacks.clone();
+        move |event: &Event| {
+            if let Event::OptimisticRollback { command, error } = event {
+                rollbacks.borrow_mut().push((*command, error.clone()))
+            }
+        }
+    });
+
+    sp::resume().await.unwrap();
+
+    // the track is shorter, the seek lands at its end and the next track starts,
+    // the state of the pause after it is the first one to judge it
+    let started = std::time::Instant::now();
+    let store = player_store();
+    let (seek, pause) = tokio::join!(store.send(Command::Seek(10_000_000)), async {
+        tokio::task::yield_now().await;
+        sp::pause().await
+    });
+    assert_eq!((seek, pause), (Err(sp::SdkError::Unconfirmed), Ok(())));
+    assert!(started.elapsed() < std::time::Duration::from_millis(sp::STATE_TIMEOUT_MS.into()));
+    assert!(!player_store().is_pending());
+    assert!(player_store().get().position().unwrap() < 1000);
+    assert_eq!(current_name(&player), "Second");
+    assert_eq!(
+        *rollbacks.borrow(),
+        [(Command::Seek(10_000_000), sp::SdkError::Unconfirmed)]
+    );
+    assert!(remove_event_listener(listener));
+}
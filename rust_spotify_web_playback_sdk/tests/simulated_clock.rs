@@ -0,0 +1,48 @@
+//! The simulated player's clock in Node, run it with `cargo test --target wasm32-unknown-unknown --test simulated_clock`.
+//! Natively the tracks only end when the player is called, in the browser they end on time.
+#![cfg(target_arch = "wasm32")]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rust_spotify_web_playback_sdk::{
+    backend::{self, SdkEvent},
+    js_wrapper, prelude as sp,
+    simulated::{self, SimulatedPlayer},
+};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+async fn tracks_end_without_calls() {
+    let player = SimulatedPlayer::new(vec![
+        simulated::track("1", "First", 50),
+        simulated::track("2", "Second", 50),
+    ]);
+    backend::set_backend(player.clone());
+    sp::init(|| "token".into(), || {}, "simulated", 0.5, false);
+    sp::connect().await.unwrap();
+    let states = Rc::new(RefCell::new(Vec::new()));
+    backend::add_listener("player_state_changed", {
+        let states = states.clone();
+        move |event| {
+            if let SdkEvent::PlayerStateChanged(state) = event {
+                states
+                    .borrow_mut()
+                    .push(state.map(|state| (state.track_window.current_track.name, state.paused)));
+            }
+        }
+    })
+    .unwrap();
+    sp::resume().await.unwrap();
+
+    JsFuture::from(js_wrapper::sleep(200)).await.unwrap();
+    assert_eq!(
+        *states.borrow(),
+        [
+            Some(("First".to_string(), false)),
+            Some(("Second".to_string(), false)),
+            // the end of the queue
+            Some(("Second".to_string(), true)),
+        ]
+    );
+}
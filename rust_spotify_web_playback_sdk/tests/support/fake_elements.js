@@ -0,0 +1,64 @@
+// Just enough of the custom elements API to run `<spotify-player>` in Node, elements are never attached to a document.
+// `fetch` is replaced too, every request gets a token, like the `token-endpoint` of the element would return.
+// The token is refreshed an hour later, `install` of `fake_spotify.js` keeps that timer from holding Node open.
+
+const registry = new Map();
+let tokenRequests = 0;
+
+class FakeElement {
+    constructor() {
+        this.attributes = new Map();
+        this.events = [];
+    }
+
+    getAttribute(name) {
+        return this.attributes.has(name) ? this.attributes.get(name) : null;
+    }
+
+    dispatchEvent(event) {
+        this.events.push(event.type);
+        return true;
+    }
+}
+
+function installElements() {
+    if (globalThis.customElements) {
+        return;
+    }
+    globalThis.HTMLElement = FakeElement;
+    globalThis.customElements = {
+        get: tag => registry.get(tag),
+        define: (tag, element) => registry.set(tag, element),
+    };
+    globalThis.fetch = async request => {
+        tokenRequests += 1;
+        const response = new Response(JSON.stringify({ access_token: "element token", expires_in: 3600 }), {
+            headers: { "content-type": "application/json" },
+        });
+        // reqwest reads the url of the response
+        Object.defineProperty(response, "url", { value: request.url });
+        return response;
+    };
+}
+
+// creates an element with the attributes, then calls its `connectedCallback` like appending it to the page does
+function connectElement(tag, attributes) {
+    const element = new (registry.get(tag))();
+    for (const [name, value] of Object.entries(attributes)) {
+        element.attributes.set(name, value);
+    }
+    element.connectedCallback();
+    return element;
+}
+
+function dispatchedEvents(element) {
+    return element.events;
+}
+
+function takeTokenRequests() {
+    const requests = tokenRequests;
+    tokenRequests = 0;
+    return requests;
+}
+
+export { installElements, connectElement, dispatchedEvents, takeTokenRequests };
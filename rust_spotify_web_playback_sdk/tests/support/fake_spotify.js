@@ -0,0 +1,363 @@
+// A scriptable stand-in for the Web Playback SDK, so the wasm tests run in Node without a browser or the network.
+// `install` puts a fake `window`, `document` and `Spotify.Player` on the global object, appending the SDK script
+// calls `window.onSpotifyWebPlaybackSDKReady` instead of downloading it.
+
+const EVENTS = [
+    "ready",
+    "not_ready",
+    "player_state_changed",
+    "autoplay_failed",
+    "initialization_error",
+    "authentication_error",
+    "account_error",
+    "playback_error",
+];
+
+const fake = {
+    player: null,
+    calls: [],
+    connectResult: true,
+    failures: new Map(),
+    reactions: new Map(),
+    holds: new Set(),
+    held: new Map(),
+    currentState: null,
+};
+
+class Player {
+    constructor(options) {
+        this.options = options;
+        this.listeners = new Map(EVENTS.map(event => [event, []]));
+        this.volume = options.volume;
+        fake.player = this;
+    }
+
+    addListener(event, callback) {
+        if (!this.listeners.has(event)) {
+            return false;
+        }
+        this.listeners.get(event).push(callback);
+        return true;
+    }
+
+    removeListener(event, callback) {
+        const listeners = this.listeners.get(event);
+        if (!listeners || listeners.length === 0) {
+            return false;
+        }
+        if (callback === undefined) {
+            this.listeners.set(event, []);
+            return true;
+        }
+        const kept = listeners.filter(listener => listener !== callback);
+        this.listeners.set(event, kept);
+        return kept.length !== listeners.length;
+    }
+
+    connect() {
+        return this.call("connect", [], () => fake.connectResult);
+    }
+
+    disconnect() {
+        record("disconnect", []);
+    }
+
+    getCurrentState() {
+        return this.call("getCurrentState", [], () => fake.currentState);
+    }
+
+    setName(name) {
+        return this.call("setName", [name], () => {
+            this.options.name = name;
+        });
+    }
+
+    getVolume() {
+        return this.call("getVolume", [], () => this.volume);
+    }
+
+    setVolume(volume) {
+        return this.call("setVolume", [volume], () => {
+            this.volume = volume;
+        });
+    }
+
+    pause() {
+        return this.call("pause", []);
+    }
+
+    resume() {
+        return this.call("resume", []);
+    }
+
+    togglePlay() {
+        return this.call("togglePlay", []);
+    }
+
+    seek(positionMs) {
+        return this.call("seek", [positionMs]);
+    }
+
+    previousTrack() {
+        return this.call("previousTrack", []);
+    }
+
+    nextTrack() {
+        return this.call("nextTrack", []);
+    }
+
+    activateElement() {
+        return this.call("activateElement", []);
+    }
+
+    // records the call, waits for `release` if it was held,
+    // then rejects if a failure was scripted, or resolves and emits the scripted reaction after it like the SDK does
+    call(method, args, resolve = () => undefined) {
+        record(method, args);
+        if (fake.holds.delete(method)) {
+            return new Promise(release => fake.held.set(method, release)).then(() =>
+                this.settle(method, resolve)
+            );
+        }
+        return this.settle(method, resolve);
+    }
+
+    settle(method, resolve) {
+        const failure = fake.failures.get(method);
+        if (failure !== undefined) {
+            fake.failures.delete(method);
+            return Promise.reject(new Error(failure));
+        }
+        const result = resolve();
+        const reaction = fake.reactions.get(method);
+        if (reaction !== undefined) {
+            fake.reactions.delete(method);
+            setTimeout(() => emit(reaction.event, reaction.payload), 0);
+        }
+        return Promise.resolve(result);
+    }
+}
+
+function record(method, args) {
+    fake.calls.push(args.length === 0 ? method : `${method}(${args.join(", ")})`);
+}
+
+function install() {
+    if (globalThis.Spotify) {
+        return;
+    }
+    globalThis.window = globalThis;
+    // `web_sys::window()` checks that the global object is a `Window`
+    globalThis.Window = class Window {
+        static [Symbol.hasInstance](value) {
+            return value === globalThis;
+        }
+    };
+    eventTarget(globalThis);
+    Object.defineProperty(globalThis, "navigator", {
+        value: { onLine: true },
+        configurable: true,
+        writable: true,
+    });
+    globalThis.Spotify = { Player };
+    globalThis.document = eventTarget({
+        visibilityState: "visible",
+        createElement: tag => ({ tagName: tag }),
+        head: {
+            appendChild: script => {
+                if (script.src && script.src.includes("sdk.scdn.co")) {
+                    setTimeout(() => window.onSpotifyWebPlaybackSDKReady(), 0);
+                }
+            },
+        },
+    });
+    // the token refresh timers don't keep Node running
+    const setTimeout = globalThis.setTimeout;
+    globalThis.setTimeout = (callback, ms, ...args) => {
+        const timer = setTimeout(callback, ms, ...args);
+        if (ms > 60000) {
+            timer.unref();
+        }
+        return timer;
+    };
+}
+
+function eventTarget(object) {
+    const target = new EventTarget();
+    object.addEventListener = target.addEventListener.bind(target);
+    object.removeEventListener = target.removeEventListener.bind(target);
+    object.dispatchEvent = target.dispatchEvent.bind(target);
+    return object;
+}
+
+// the browser going offline or back online
+function setOnline(online) {
+    navigator.onLine = online;
+    window.dispatchEvent(new Event(online ? "online" : "offline"));
+}
+
+// the page going to the background or back
+function setVisible(visible) {
+    document.visibilityState = visible ? "visible" : "hidden";
+    document.dispatchEvent(new Event("visibilitychange"));
+}
+
+// forgets the calls and the scripted results, the listeners stay
+function reset() {
+    fake.calls = [];
+    fake.connectResult = true;
+    fake.failures.clear();
+    fake.reactions.clear();
+    fake.holds.clear();
+    fake.held.clear();
+    fake.currentState = null;
+    navigator.onLine = true;
+    document.visibilityState = "visible";
+}
+
+function emit(event, payload) {
+    const listeners = fake.player ? fake.player.listeners.get(event) || [] : [];
+    for (const listener of [...listeners]) {
+        listener(payload);
+    }
+    return listeners.length;
+}
+
+function takeCalls() {
+    const calls = fake.calls;
+    fake.calls = [];
+    return calls;
+}
+
+function setConnectResult(result) {
+    fake.connectResult = result;
+}
+
+function failNext(method, message) {
+    fake.failures.set(method, message);
+}
+
+function emitAfter(method, event, payload) {
+    fake.reactions.set(method, { event, payload });
+}
+
+// the next call of `method` doesn't settle until `release(method)`
+function holdNext(method) {
+    fake.holds.add(method);
+}
+
+function release(method) {
+    const settle = fake.held.get(method);
+    fake.held.delete(method);
+    if (settle !== undefined) {
+        settle();
+    }
+}
+
+function setCurrentState(state) {
+    fake.currentState = state;
+}
+
+function playerName() {
+    return fake.player ? fake.player.options.name : undefined;
+}
+
+// asks the player for a token like the SDK does before connecting
+function requestToken() {
+    return new Promise(resolve => fake.player.options.getOAuthToken(resolve));
+}
+
+function tick() {
+    return new Promise(resolve => setTimeout(resolve, 0));
+}
+
+function track(id, name) {
+    return {
+        album: { uri: `spotify:album:${id}`, name: `${name} (album)`, images: [] },
+        artists: [{ uri: `spotify:artist:${id}`, url: null, name: "Fake artist" }],
+        duration_ms: 180000,
+        id,
+        is_playable: true,
+        linked_from: null,
+        media_type: "audio",
+        metadata: null,
+        name,
+        track_type: "audio",
+        uid: `uid-${id}`,
+        uri: `spotify:track:${id}`,
+    };
+}
+
+// the `player_state_changed` payload with `trackId` as the current track
+function stateChange(trackId, paused, position) {
+    return {
+        context: { uri: "spotify:playlist:fake", metadata: null },
+        disallows: {
+            pausing: paused,
+            resuming: !paused,
+            seeking: false,
+            skipping_next: false,
+            skipping_prev: false,
+        },
+        duration: 180000,
+        loading: false,
+        paused,
+        playback_features: {
+            hifi_status: "NONE",
+            playback_speed: { current: 1, restricted: false, selected: 1 },
+            signal_ids: null,
+        },
+        playback_id: "fake",
+        playback_quality: "VERY_HIGH",
+        playback_speed: 1,
+        position,
+        repeat_mode: 0,
+        restrictions: {
+            disallow_pausing_reasons: paused ? ["already_paused"] : null,
+            disallow_resuming_reasons: paused ? null : ["not_paused"],
+        },
+        shuffle: false,
+        shuffle_mode: 0,
+        timestamp: Date.now(),
+        track_window: {
+            current_track: track(trackId, `Track ${trackId}`),
+            next_tracks: [],
+            previous_tracks: [],
+        },
+    };
+}
+
+// the same state as `getCurrentState` resolves with it
+function state(trackId, paused, position) {
+    const change = stateChange(trackId, paused, position);
+    return {
+        context: change.context,
+        disallows: change.disallows,
+        loading: false,
+        paused,
+        position,
+        repeat_mode: 0,
+        shuffle: false,
+        track_window: change.track_window,
+    };
+}
+
+export {
+    install,
+    reset,
+    emit,
+    takeCalls,
+    setConnectResult,
+    failNext,
+    emitAfter,
+    holdNext,
+    release,
+    setOnline,
+    setVisible,
+    setCurrentState,
+    playerName,
+    requestToken,
+    tick,
+    stateChange,
+    state,
+};
@@ -0,0 +1,151 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rust_spotify_web_playback_sdk::{
+    error::SdkError,
+    events::{add_event_listener, remove_event_listener, Event},
+    token::{AccessToken, TokenCache, MIN_REFRESH_DELAY_MS},
+};
+
+#[tokio::test]
+async fn concurrent_refreshes_are_deduplicated() {
+    let calls = Rc::new(RefCell::new(0));
+    let cache = TokenCache::new({
+        let calls = calls.clone();
+        move || {
+            *calls.borrow_mut() += 1;
+            let token = format!("token {}", calls.borrow());
+            async move {
+                tokio::task::yield_now().await;
+                Ok(AccessToken {
+                    access_token: token,
+                    expires_in: 3600,
+                })
+            }
+        }
+    });
+    assert!(cache.needs_refresh());
+
+    let (first, second) = tokio::join!(cache.refresh(), cache.refresh());
+    assert_eq!(first.unwrap(), "token 1");
+    assert_eq!(second.unwrap(), "token 1");
+    assert_eq!(*calls.borrow(), 1);
+    assert_eq!(cache.token().as_deref(), Some("token 1"));
+    assert!(!cache.needs_refresh());
+
+    assert_eq!(cache.refresh().await.unwrap(), "token 2");
+    assert_eq!(*calls.borrow(), 2);
+}
+
+#[tokio::test]
+async fn refreshes_emit_events() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let events = events.clone();
+        move |event: &Event| events.borrow_mut().push(event.clone())
+    });
+    let fail = Rc::new(RefCell::new(false));
+    let cache = TokenCache::new({
+        let fail = fail.clone();
+        move || {
+            let fail = *fail.borrow();
+            async move {
+                if fail {
+                    Err(SdkError::Request("offline".into()))
+                } else {
+                    Ok(AccessToken {
+                        access_token: "token".into(),
+                        expires_in: 30,
+                    })
+                }
+            }
+        }
+    });
+
+    cache.refresh().await.unwrap();
+    // 30 seconds is inside the default refresh ahead time
+    assert!(cache.needs_refresh());
+    *fail.borrow_mut() = true;
+    assert!(cache.refresh().await.is_err());
+    assert_eq!(cache.token().as_deref(), Some("token"));
+
+    let events = events.borrow();
+    assert!(matches!(
+        events[0],
+        Event::TokenRefreshed { expires_at } if Some(expires_at) == cache.expires_at()
+    ));
+    assert_eq!(
+        events[1],
+        Event::TokenRefreshFailed {
+            error: SdkError::Request("offline".into())
+        }
+    );
+    assert!(remove_event_listener(listener));
+}
+
+#[test]
+fn set_token_respects_the_refresh_ahead_time() {
+    let cache =
+        TokenCache::new(|| async { Err::<AccessToken, _>(SdkError::Request("unused".into())) })
+            .with_refresh_ahead(0);
+    let in_an_hour = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+        + 3_600_000;
+    cache.set_token("token", in_an_hour);
+    assert_eq!(cache.token().as_deref(), Some("token"));
+    assert_eq!(cache.expires_at(), Some(in_an_hour));
+    assert!(!cache.needs_refresh());
+}
+
+#[tokio::test]
+async fn the_provider_needs_a_first_refresh() {
+    let cache = TokenCache::new(|| async {
+        Ok(AccessToken {
+            access_token: "token".into(),
+            expires_in: 3600,
+        })
+    });
+    let mut provider = cache.provider();
+    // nothing refreshes in the background outside of the browser
+    assert_eq!(provider(), "");
+    assert_eq!(cache.token(), None);
+
+    cache.refresh().await.unwrap();
+    assert_eq!(provider(), "token");
+}
+
+#[tokio::test]
+async fn short_lived_tokens_are_not_refreshed_in_a_loop() {
+    let expires_in = Rc::new(RefCell::new(3600));
+    let cache = TokenCache::new({
+        let expires_in = expires_in.clone();
+        move || {
+            let expires_in = *expires_in.borrow();
+            async move {
+                Ok(AccessToken {
+                    access_token: "token".into(),
+                    expires_in,
+                })
+            }
+        }
+    });
+    assert_eq!(cache.refresh_delay_ms(), None);
+
+    // a minute before it expires
+    cache.refresh().await.unwrap();
+    let delay = cache.refresh_delay_ms().unwrap();
+    assert!((3_539_000..=3_540_000).contains(&delay));
+
+    // 30 seconds is shorter than the refresh ahead time, so half of it
+    *expires_in.borrow_mut() = 30;
+    cache.refresh().await.unwrap();
+    let delay = cache.refresh_delay_ms().unwrap();
+    assert!((14_000..=15_000).contains(&delay));
+
+    *expires_in.borrow_mut() = 2;
+    cache.refresh().await.unwrap();
+    assert_eq!(cache.refresh_delay_ms(), Some(MIN_REFRESH_DELAY_MS));
+}
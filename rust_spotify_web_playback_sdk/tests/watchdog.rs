@@ -0,0 +1,193 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use rust_spotify_web_playback_sdk::{
+    events::{add_event_listener, remove_event_listener, Event},
+    simulated,
+    structs::web_playback::State,
+    watchdog::{Checker, RecoveryStep, Stall, StallReason, WatchdogOptions},
+};
+use serde_json::json;
+
+fn state(position: i32, paused: bool, loading: bool) -> State {
+    serde_json::from_value(json!({
+        "context": { "uri": "spotify:playlist:watchdog", "metadata": null },
+        "disallows": {},
+        "loading": loading,
+        "paused": paused,
+        "position": position,
+        "repeat_mode": 0,
+        "shuffle": false,
+        "track_window": {
+            "current_track": simulated::track("1", "First", 180_000),
+            "previous_tracks": [],
+            "next_tracks": [],
+        },
+    }))
+    .unwrap()
+}
+
+fn checker(recover: bool) -> Checker {
+    Checker::new(WatchdogOptions {
+        stalled_after_ms: 5000,
+        loading_after_ms: 10_000,
+        recover,
+        ..Default::default()
+    })
+}
+
+fn stall(reason: StallReason, position_ms: i32, step: Option<RecoveryStep>) -> Option<Stall> {
+    Some(Stall {
+        reason,
+        position_ms,
+        step,
+    })
+}
+
+#[test]
+fn a_position_that_doesnt_move_is_a_stall() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let events = events.clone();
+        move |event: &Event| events.borrow_mut().push(event.clone())
+    });
+    let mut checker = checker(false);
+
+    assert_eq!(checker.check(0.0, Some(&state(1000, false, false))), None);
+    assert_eq!(
+        checker.check(1000.0, Some(&state(2000, false, false))),
+        None
+    );
+    assert_eq!(
+        checker.check(5000.0, Some(&state(2000, false, false))),
+        None
+    );
+    assert_eq!(
+        checker.check(6000.0, Some(&state(2000, false, false))),
+        stall(StallReason::NotProgressing, 2000, None)
+    );
+    // reported again only once it lasted another `stalled_after_ms`
+    assert_eq!(
+        checker.check(7000.0, Some(&state(2000, false, false))),
+        None
+    );
+    assert_eq!(
+        checker.check(11_000.0, Some(&state(2000, false, false))),
+        stall(StallReason::NotProgressing, 2000, None)
+    );
+    assert_eq!(
+        *events.borrow(),
+        [
+            Event::PlaybackStalled {
+                reason: StallReason::NotProgressing,
+                position_ms: 2000,
+            },
+            Event::PlaybackStalled {
+                reason: StallReason::NotProgressing,
+                position_ms: 2000,
+            },
+        ]
+    );
+    assert!(remove_event_listener(listener));
+}
+
+#[test]
+fn paused_playback_and_no_playback_are_not_stalls() {
+    let mut checker = checker(true);
+    assert_eq!(checker.check(0.0, Some(&state(1000, true, false))), None);
+    assert_eq!(
+        checker.check(60_000.0, Some(&state(1000, true, false))),
+        None
+    );
+
+    assert_eq!(
+        checker.check(61_000.0, Some(&state(1000, false, false))),
+        None
+    );
+    assert_eq!(checker.check(62_000.0, None), None);
+    // no playback forgets the position, so this is the first time it's seen
+    assert_eq!(
+        checker.check(67_000.0, Some(&state(1000, false, false))),
+        None
+    );
+}
+
+#[test]
+fn loading_for_too_long_is_a_stall() {
+    let mut checker = checker(false);
+    assert_eq!(checker.check(0.0, Some(&state(0, false, true))), None);
+    assert_eq!(checker.check(9000.0, Some(&state(0, false, true))), None);
+    assert_eq!(
+        checker.check(10_000.0, Some(&state(0, false, true))),
+        stall(StallReason::Loading, 0, None)
+    );
+
+    // the loading flag and the position come from the same state
+    assert_eq!(checker.check(11_000.0, Some(&state(0, false, false))), None);
+    assert_eq!(checker.check(12_000.0, Some(&state(0, false, true))), None);
+    assert_eq!(checker.check(21_000.0, Some(&state(0, false, true))), None);
+    assert_eq!(
+        checker.check(22_000.0, Some(&state(0, false, true))),
+        stall(StallReason::Loading, 0, None)
+    );
+}
+
+#[test]
+fn recovery_escalates_and_resets_once_playback_moves() {
+    let steps = Rc::new(RefCell::new(Vec::new()));
+    let listener = add_event_listener({
+        let steps = steps.clone();
+        move |event: &Event| {
+            if let Event::StallRecovery { step } = event {
+                steps.borrow_mut().push(*step)
+            }
+        }
+    });
+    let mut checker = checker(true);
+    let stuck = state(3000, false, false);
+
+    assert_eq!(checker.check(0.0, Some(&stuck)), None);
+    let found: Vec<_> = [5000.0, 10_000.0, 15_000.0, 20_000.0]
+        .into_iter()
+        .map(|now| checker.check(now, Some(&stuck)))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            stall(StallReason::NotProgressing, 3000, Some(RecoveryStep::Seek)),
+            stall(
+                StallReason::NotProgressing,
+                3000,
+                Some(RecoveryStep::PauseResume)
+            ),
+            stall(
+                StallReason::NotProgressing,
+                3000,
+                Some(RecoveryStep::Reconnect)
+            ),
+            // everything was tried
+            stall(StallReason::NotProgressing, 3000, None),
+        ]
+    );
+
+    // progress starts the sequence over
+    assert_eq!(
+        checker.check(21_000.0, Some(&state(4000, false, false))),
+        None
+    );
+    assert_eq!(
+        checker.check(26_000.0, Some(&state(4000, false, false))),
+        stall(StallReason::NotProgressing, 4000, Some(RecoveryStep::Seek))
+    );
+    assert_eq!(
+        *steps.borrow(),
+        [
+            RecoveryStep::Seek,
+            RecoveryStep::PauseResume,
+            RecoveryStep::Reconnect,
+            RecoveryStep::Seek,
+        ]
+    );
+    assert!(remove_event_listener(listener));
+}
@@ -0,0 +1,373 @@
+#![cfg(all(feature = "web_api", not(target_arch = "wasm32")))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use mockito::Matcher;
+use rust_spotify_web_playback_sdk::{
+    backend,
+    error::SdkError,
+    events::{add_event_listener, Event},
+    prelude as sp,
+    simulated::SimulatedPlayer,
+    structs::RepeatMode,
+    web_api::{AutoActivate, Offset, PlayRequest, StartPlayback, WebApi},
+};
+
+fn client(server: &mockito::ServerGuard) -> WebApi {
+    WebApi::new(|| "token".to_string()).with_base_url(server.url())
+}
+
+#[tokio::test]
+async fn transfer_playback_sends_device_id() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("PUT", "/me/player")
+        .match_header("authorization", "Bearer token")
+        .match_body(Matcher::Json(
+            serde_json::json!({ "device_ids": ["device"], "play": true }),
+        ))
+        .with_status(204)
+        .create_async()
+        .await;
+
+    client(&server)
+        .transfer_playback("device", true)
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn start_playback_with_context_and_offset() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("PUT", "/me/player/play")
+        .match_query(Matcher::UrlEncoded("device_id".into(), "device".into()))
+        .match_body(Matcher::Json(serde_json::json!({
+            "context_uri": "spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            "offset": { "position": 3 },
+            "position_ms": 1000
+        })))
+        .with_status(204)
+        .create_async()
+        .await;
+
+    let playback = StartPlayback {
+        context_uri: Some("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M".into()),
+        offset: Some(Offset::Position(3)),
+        position_ms: Some(1000),
+        ..Default::default()
+    };
+    client(&server)
+        .start_playback(Some("device"), &playback)
+        .await
+        .unwrap();
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn shuffle_repeat_and_queue_use_query_parameters() {
+    let mut server = mockito::Server::new_async().await;
+    let shuffle = server
+        .mock("PUT", "/me/player/shuffle")
+        .match_query(Matcher::UrlEncoded("state".into(), "true".into()))
+        .with_status(204)
+        .create_async()
+        .await;
+    let repeat = server
+        .mock("PUT", "/me/player/repeat")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("state".into(), "context".into()),
+            Matcher::UrlEncoded("device_id".into(), "device".into()),
+        ]))
+        .with_status(204)
+        .create_async()
+        .await;
+    let queue = server
+        .mock("POST", "/me/player/queue")
+        .match_query(Matcher::UrlEncoded(
+            "uri".into(),
+            "spotify:track:4iV5W9uYEdYUVa79Axb7Rh".into(),
+        ))
+        .with_status(204)
+        .create_async()
+        .await;
+
+    let api = client(&server);
+    api.set_shuffle(true, None).await.unwrap();
+    api.set_repeat(RepeatMode::Context, Some("device"))
+        .await
+        .unwrap();
+    api.add_to_queue("spotify:track:4iV5W9uYEdYUVa79Axb7Rh", None)
+        .await
+        .unwrap();
+    shuffle.assert_async().await;
+    repeat.assert_async().await;
+    queue.assert_async().await;
+}
+
+#[tokio::test]
+async fn get_queue_returns_tracks() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/me/player/queue")
+        .with_status(200)
+        .with_body(
+            serde_json::json!({
+                "currently_playing": {
+                    "album": {
+                        "uri": "spotify:album:1",
+                        "name": "Album",
+                        "images": [{ "url": "https://i.scdn.co/image/1", "width": 640, "height": 640 }]
+                    },
+                    "artists": [{ "uri": "spotify:artist:1", "name": "Artist" }],
+                    "duration_ms": 1000,
+                    "id": "1",
+                    "name": "Track",
+                    "type": "track",
+                    "uri": "spotify:track:1"
+                },
+                "queue": [{
+                    "show": { "uri": "spotify:show:1", "name": "Show", "images": [] },
+                    "images": [{ "url": "https://i.scdn.co/image/2", "width": 64, "height": 64 }],
+                    "duration_ms": 2000,
+                    "id": "2",
+                    "name": "Episode",
+                    "type": "episode",
+                    "uri": "spotify:episode:2"
+                }, {
+                    "artists": [{ "uri": "spotify:author:3", "name": "Author" }],
+                    "duration_ms": 3000,
+                    "id": "3",
+                    "name": "Chapter",
+                    "type": "chapter",
+                    "uri": "spotify:chapter:3"
+                }, {
+                    "duration_ms": 4000,
+                    "name": "Something new",
+                    "type": "hologram",
+                    "uri": "spotify:hologram:4"
+                }]
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let queue = client(&server).get_queue().await.unwrap();
+    let current = queue.currently_playing.unwrap();
+    assert_eq!(current.uri, "spotify:track:1");
+    assert_eq!(current.album.images[0].width, Some(640));
+    assert_eq!(current.artists[0].name, "Artist");
+    assert_eq!(
+        (current.media_type.as_str(), current.track_type.as_str()),
+        ("audio", "audio")
+    );
+    let types: Vec<_> = queue
+        .queue
+        .iter()
+        .map(|item| (item.media_type.as_str(), item.track_type.as_str()))
+        .collect();
+    assert_eq!(
+        types,
+        [("audio", "episode"), ("audio", "chapter"), ("", "hologram")]
+    );
+    assert_eq!(queue.queue[0].album.name, "Show");
+    assert_eq!(
+        queue.queue[0].album.images[0].url,
+        "https://i.scdn.co/image/2"
+    );
+}
+
+#[tokio::test]
+async fn get_devices_and_api_errors() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/me/player/devices")
+        .with_status(200)
+        .with_body(
+            serde_json::json!({
+                "devices": [{
+                    "id": "device",
+                    "is_active": false,
+                    "is_private_session": false,
+                    "is_restricted": false,
+                    "name": "example player",
+                    "type": "Computer",
+                    "volume_percent": 100,
+                    "supports_volume": true
+                }]
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    server
+        .mock("PUT", "/me/player/shuffle")
+        .match_query(Matcher::Any)
+        .with_status(404)
+        .with_body(
+            r#"{"error":{"status":404,"message":"Player command failed: No active device found","reason":"NO_ACTIVE_DEVICE"}}"#,
+        )
+        .create_async()
+        .await;
+
+    let api = client(&server);
+    let devices = api.get_devices().await.unwrap();
+    assert_eq!(devices[0].id.as_deref(), Some("device"));
+    assert_eq!(devices[0].device_type, "Computer");
+
+    assert_eq!(
+        api.set_shuffle(false, None).await,
+        Err(SdkError::Api {
+            status: 404,
+            message: "Player command failed: No active device found".into(),
+            reason: Some("NO_ACTIVE_DEVICE".into()),
+        })
+    );
+}
+
+#[tokio::test]
+async fn preflight_reports_missing_scopes_and_product() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/me")
+        .with_status(200)
+        .with_body(r#"{"id":"user","display_name":"User","product":"free","country":"HU"}"#)
+        .create_async()
+        .await;
+
+    let api = client(&server);
+    let report = api
+        .preflight_with_scopes("streaming user-read-private")
+        .await
+        .unwrap();
+    assert_eq!(
+        report.missing_scopes,
+        vec!["user-read-email", "user-modify-playback-state"]
+    );
+    assert_eq!(report.product.as_deref(), Some("free"));
+    assert_eq!(report.country.as_deref(), Some("HU"));
+    assert!(!report.is_premium());
+    assert!(!report.is_ok());
+
+    // without the granted scopes only the profile fields are checked
+    let report = api.preflight().await.unwrap();
+    assert_eq!(report.missing_scopes, vec!["user-read-email"]);
+}
+
+#[tokio::test]
+async fn preflight_of_a_premium_account() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/me")
+        .with_status(200)
+        .with_body(
+            r#"{"id":"user","display_name":null,"email":"user@example.com","product":"premium","country":"US"}"#,
+        )
+        .create_async()
+        .await;
+
+    let report = client(&server)
+        .preflight_with_scopes(&rust_spotify_web_playback_sdk::SCOPES.join(" "))
+        .await
+        .unwrap();
+    assert!(report.missing_scopes.is_empty());
+    assert!(report.is_ok());
+}
+
+/// Records the events the crate emits
+fn record_events() -> Rc<RefCell<Vec<Event>>> {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorded = events.clone();
+    add_event_listener(move |event| recorded.borrow_mut().push(event.clone()));
+    events
+}
+
+fn retries(max_retries: u32) -> AutoActivate {
+    AutoActivate {
+        play: true,
+        max_retries,
+        retry_delay_ms: 0,
+    }
+}
+
+#[tokio::test]
+async fn activate_retries_until_the_device_is_known() {
+    let mut server = mockito::Server::new_async().await;
+    let not_found = server
+        .mock("PUT", "/me/player")
+        .with_status(404)
+        .with_body(r#"{"error":{"status":404,"message":"Device not found"}}"#)
+        .expect(2)
+        .create_async()
+        .await;
+    let transferred = server
+        .mock("PUT", "/me/player")
+        .match_body(Matcher::Json(
+            serde_json::json!({ "device_ids": ["device"], "play": true }),
+        ))
+        .with_status(204)
+        .expect(1)
+        .create_async()
+        .await;
+    let events = record_events();
+
+    assert_eq!(
+        client(&server).activate("device", &retries(5)).await,
+        Ok(())
+    );
+    not_found.assert_async().await;
+    transferred.assert_async().await;
+    assert_eq!(
+        *events.borrow(),
+        [Event::DeviceActivated {
+            device_id: "device".into()
+        }]
+    );
+}
+
+#[tokio::test]
+async fn activate_gives_up_after_the_retries() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("PUT", "/me/player")
+        .with_status(502)
+        .with_body(r#"{"error":{"status":502,"message":"Bad gateway"}}"#)
+        .expect(3)
+        .create_async()
+        .await;
+    let events = record_events();
+
+    let error = SdkError::Api {
+        status: 502,
+        message: "Bad gateway".into(),
+        reason: None,
+    };
+    assert_eq!(
+        client(&server).activate("device", &retries(2)).await,
+        Err(error.clone())
+    );
+    mock.assert_async().await;
+    assert_eq!(
+        *events.borrow(),
+        [Event::ActivationFailed {
+            device_id: "device".into(),
+            error
+        }]
+    );
+}
+
+#[tokio::test]
+async fn play_without_tracks_fails_before_sending() {
+    backend::set_backend(SimulatedPlayer::new(Vec::new()));
+    sp::init(|| "token".into(), || {}, "simulated", 1.0, false);
+    let request = PlayRequest::Tracks {
+        uris: Vec::new(),
+        position_ms: None,
+    };
+    assert!(matches!(
+        sp::play(request).await,
+        Err(SdkError::InvalidRequest(_))
+    ));
+}